@@ -0,0 +1,2 @@
+drop table equipment;
+drop table magic_items;
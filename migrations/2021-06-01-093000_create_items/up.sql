@@ -0,0 +1,21 @@
+create table equipment (
+    id int not null primary key,
+    name varchar(64) not null,
+    category varchar(32) not null,
+    subcategory varchar(32),
+    cost varchar(32),
+    weight float,
+    damage varchar(32),
+    armor_class varchar(32),
+    properties text not null,
+    description text not null
+);
+
+create table magic_items (
+    id int not null primary key,
+    name varchar(64) not null,
+    category varchar(32) not null,
+    rarity varchar(32) not null,
+    attunement boolean not null,
+    description text not null
+);
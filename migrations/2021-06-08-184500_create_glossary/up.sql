@@ -0,0 +1,6 @@
+create table glossary (
+    id int not null primary key,
+    term varchar(64) not null,
+    kind varchar(16) not null,
+    definition text not null
+);
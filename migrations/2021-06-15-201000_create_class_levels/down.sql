@@ -0,0 +1 @@
+drop table class_levels;
@@ -0,0 +1,16 @@
+create table class_levels (
+    id int not null primary key,
+    class varchar(32) not null,
+    level int not null,
+    cantrips_known int,
+    spells_known int,
+    slots_1 int not null,
+    slots_2 int not null,
+    slots_3 int not null,
+    slots_4 int not null,
+    slots_5 int not null,
+    slots_6 int not null,
+    slots_7 int not null,
+    slots_8 int not null,
+    slots_9 int not null
+);
@@ -0,0 +1,30 @@
+-- SQLite can't drop a column that references another table, so spells is
+-- rebuilt without it
+create table old_spells (
+    id int not null primary key,
+    name varchar(32) not null,
+    description text not null,
+    higher_level text,
+    range text,
+    verbal boolean not null,
+    somatic boolean not null,
+    material boolean not null,
+    material_text text,
+    ritual boolean not null,
+    duration varchar(32) not null,
+    concentration boolean not null,
+    casting_time varchar(32) not null,
+    level int not null,
+    school varchar(32) not null,
+    classes text not null,
+    subclasses text not null
+);
+insert into old_spells
+    select id, name, description, higher_level, range, verbal, somatic, material,
+        material_text, ritual, duration, concentration, casting_time, level, school,
+        classes, subclasses
+    from spells;
+drop table spells;
+alter table old_spells rename to spells;
+
+drop table sources;
@@ -0,0 +1,9 @@
+create table sources (
+    id int not null primary key,
+    name varchar(64) not null unique,
+    active boolean not null default 1
+);
+
+insert into sources (id, name, active) values (0, 'SRD', 1);
+
+alter table spells add column source_id int not null default 0 references sources (id);
@@ -0,0 +1,9 @@
+create table imports (
+    id int not null primary key,
+    imported_at timestamp not null,
+    importer_version varchar(16) not null,
+    kind varchar(32) not null,
+    source_path text not null,
+    content_hash varchar(64) not null,
+    records int not null
+);
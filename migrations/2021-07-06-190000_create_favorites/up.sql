@@ -0,0 +1,3 @@
+create table favorites (
+    name text not null primary key
+);
@@ -0,0 +1 @@
+drop table pane_sizes;
@@ -0,0 +1,4 @@
+create table pane_sizes (
+    pane text not null primary key,
+    percent integer not null
+);
@@ -0,0 +1,17 @@
+create table old_sources (
+    id int not null primary key,
+    name varchar(64) not null unique,
+    active boolean not null default 1
+);
+insert into old_sources (id, name, active)
+    select id, name, not exists (
+        select 1 from campaign_sources
+        join campaigns on campaigns.id = campaign_sources.campaign_id
+        where campaigns.current and campaign_sources.source_id = sources.id
+            and not campaign_sources.active
+    ) from sources;
+drop table sources;
+alter table old_sources rename to sources;
+
+drop table campaign_sources;
+drop table campaigns;
@@ -0,0 +1,28 @@
+create table campaigns (
+    id int not null primary key,
+    name varchar(64) not null unique,
+    current boolean not null default 0
+);
+
+insert into campaigns (id, name, current) values (0, 'Default', 1);
+
+-- Which sources each campaign allows. Sources without a row are allowed, so
+-- newly imported ones show up in every campaign.
+create table campaign_sources (
+    campaign_id int not null references campaigns (id),
+    source_id int not null references sources (id),
+    active boolean not null,
+    primary key (campaign_id, source_id)
+);
+
+insert into campaign_sources (campaign_id, source_id, active)
+    select 0, id, active from sources where not active;
+
+-- Whether a source is active now depends on the campaign
+create table new_sources (
+    id int not null primary key,
+    name varchar(64) not null unique
+);
+insert into new_sources (id, name) select id, name from sources;
+drop table sources;
+alter table new_sources rename to sources;
@@ -0,0 +1,152 @@
+//! Prints the `Spans` the TUI draws with to a plain terminal.
+
+use tui::style::{Color, Modifier, Style};
+use tui::text::Spans;
+
+fn color(color: Color) -> Option<String> {
+    let code = match color {
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+        Color::Indexed(i) => return Some(format!("38;5;{}", i)),
+        Color::Rgb(r, g, b) => return Some(format!("38;2;{};{};{}", r, g, b)),
+        Color::Reset => return None,
+    };
+    Some(code.to_string())
+}
+
+/// The SGR escape that switches to `style`, or nothing for the default style.
+fn sgr(style: Style) -> String {
+    let mut codes = Vec::new();
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ];
+    for (modifier, code) in modifiers.iter() {
+        if style.add_modifier.contains(*modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(fg) = style.fg.and_then(color) {
+        codes.push(fg);
+    }
+
+    if codes.is_empty() {
+        String::new()
+    } else {
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+/// Word wraps `lines` to `width` columns and joins them into text, styled
+/// with ANSI escapes when `styled` is set.
+pub fn render(lines: &[Spans], width: usize, styled: bool) -> String {
+    let mut out = String::new();
+    for line in lines {
+        let mut column = 0;
+        let mut current = Style::default();
+        for span in line.0.iter() {
+            let content = span.content.replace('\n', "");
+            // Keep runs of spaces attached to the word that follows, so
+            // indentation and table padding survive
+            let mut words = Vec::new();
+            let mut start = 0;
+            for (i, c) in content.char_indices() {
+                if c == ' ' && i > 0 && !content[..i].ends_with(' ') {
+                    words.push(&content[start..i]);
+                    start = i;
+                }
+            }
+            words.push(&content[start..]);
+
+            for word in words.into_iter().filter(|word| !word.is_empty()) {
+                let mut word = word;
+                let length = word.chars().count();
+                if column > 0 && column + length > width {
+                    out.push('\n');
+                    column = 0;
+                    word = word.trim_start();
+                }
+                if styled && span.style != current {
+                    if current != Style::default() {
+                        out.push_str("\x1b[0m");
+                    }
+                    out.push_str(&sgr(span.style));
+                    current = span.style;
+                }
+                out.push_str(word);
+                column += word.chars().count();
+            }
+        }
+        if current != Style::default() {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::style::{Color, Modifier, Style};
+    use tui::text::{Span, Spans};
+
+    use super::{render, sgr};
+
+    #[test]
+    fn wraps_words_to_the_width() {
+        let lines = [Spans::from("the quick brown fox"), Spans::from("")];
+        assert_eq!(render(&lines, 10, false), "the quick\nbrown fox\n\n");
+        // Words longer than a line are left whole
+        assert_eq!(render(&[Spans::from("a disintegrate")], 5, false), "a\ndisintegrate\n");
+    }
+
+    #[test]
+    fn keeps_leading_spaces() {
+        let lines = [Spans::from("  • one"), Spans::from("3      8d6     ")];
+        assert_eq!(render(&lines, 80, false), "  • one\n3      8d6     \n");
+    }
+
+    #[test]
+    fn styles_spans_with_escapes() {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let lines = [Spans::from(vec![
+            Span::raw("a "),
+            Span::styled("bold", bold),
+            Span::raw(" b\n"),
+        ])];
+        assert_eq!(render(&lines, 80, true), "a \x1b[1mbold\x1b[0m b\n");
+        assert_eq!(render(&lines, 80, false), "a bold b\n");
+
+        let red = Spans::from(Span::styled("x", Style::default().fg(Color::Red)));
+        assert_eq!(render(&[red], 80, true), "\x1b[31mx\x1b[0m\n");
+    }
+
+    #[test]
+    fn builds_sgr_codes() {
+        let style = Style::default()
+            .fg(Color::Rgb(1, 2, 3))
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        assert_eq!(sgr(style), "\x1b[1;4;38;2;1;2;3m");
+        assert_eq!(sgr(Style::default().fg(Color::Indexed(208))), "\x1b[38;5;208m");
+        assert_eq!(sgr(Style::default().fg(Color::LightCyan)), "\x1b[96m");
+        assert_eq!(sgr(Style::default().fg(Color::Reset)), "");
+        assert_eq!(sgr(Style::default()), "");
+    }
+}
@@ -1,115 +1,742 @@
 use diesel::sqlite::SqliteConnection;
-use tui::layout::{Rect, Direction};
+use std::any::Any;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use termion::event::Key;
+use tui::layout::{Constraint, Layout, Rect};
 use tui::backend::Backend;
+use tui::style::{Modifier, Style};
+use tui::text::Spans;
+use tui::widgets::{Block, Borders, Paragraph, Tabs};
 use tui::Frame;
 
-use crate::db_utils::{build_db, establish_connection, query_spell, Query, Spell};
-use crate::components::Container;
-use crate::components_ui::{build_component_tree, SpellSearch};
-use crate::models::{Class, School};
-use log::info;
+use dnd_tui::db_utils::{build_db, connect, count_spells, is_empty, load_campaigns,
+                      load_favorites, load_glossary, load_imports, load_pane_sizes, load_sources,
+                      query_class_levels, query_items, query_spell, save_pane_sizes,
+                      set_current_campaign, set_favorite, set_source_active, Campaign,
+                      ClassLevel, GlossaryEntry, Import, ImportError, Item, ItemQuery, Progress,
+                      Query, Source, Spell};
+use crate::components::{AboutData, Container, Pane, SourceList};
+use crate::components_ui::{build_class_tree, build_component_tree, build_item_tree,
+                           export_popup, import_popup};
+use crate::config::{self, Binding};
+use crate::events::{Event, ImportEvent, Input};
+use crate::export::{self, Format};
+use dnd_tui::models::{Class, ItemCategory, Rarity, School};
 
-pub enum MoveResponse {
-    Sibling,
-    None,
-}
-
-pub enum SelectResponse {
+/// Messages components send to the app from `handle_event`, for anything
+/// that reaches past the component itself.
+pub enum Action {
+    /// The event was used and there's nothing for the app to do
+    Consumed,
     Level(i32),
+    CharacterLevel(i32),
     Class(Class),
     School(School),
-    None,
+    Category(ItemCategory),
+    Rarity(Rarity),
+    Attunement(bool),
+    Source(i32, bool),
+    /// Makes the campaign with this id current
+    Campaign(i32),
+    /// The search bar's text changed
+    Search(String),
+    /// A spell was added to (true) or removed from the favorites
+    Favorite(String, bool),
 }
 
 pub trait Stateful {
-    fn next(&mut self, direction: Direction) -> MoveResponse;
-    fn previous(&mut self, direction: Direction) -> MoveResponse;
+    /// Offers the component an event. `None` means it wasn't used, so a
+    /// parent or the app can act on it instead.
+    fn handle_event(&mut self, event: &Event<Input>, context: &Context) -> Option<Action>;
     fn hover(&mut self, activate: bool);
-    fn select(&mut self, activate: bool) -> SelectResponse;
+
+    /// Whether the component was last drawn over the cell at `x`, `y`.
+    fn contains(&self, x: u16, y: u16) -> bool;
+
+    /// Moves focus to the next pane in tree order, returning false when the
+    /// last one was already focused. A single pane never moves.
+    fn focus_next(&mut self) -> bool {
+        false
+    }
+
+    fn focus_previous(&mut self) -> bool {
+        false
+    }
+
+    /// Points focus at the first pane, without hovering it.
+    fn focus_first(&mut self) {}
+
+    fn focus_last(&mut self) {}
+
+    /// Focuses and selects `pane` if it's in this part of the tree.
+    fn focus(&mut self, _pane: Pane) -> bool {
+        false
+    }
+
+    /// Widens (or for a negative `step`, narrows) the column holding the
+    /// focused pane by `step` percent, returning the sizes to save. `None`
+    /// means there's no column to resize.
+    fn resize(&mut self, _step: i16) -> Option<Vec<(String, u16)>> {
+        None
+    }
+
+    /// The spell whose card is open in this part of the tree, if any.
+    fn open_spell(&self) -> Option<&Spell> {
+        None
+    }
+
+    /// The spell highlighted in a focused list or open on a card.
+    fn current_spell(&self) -> Option<&Spell> {
+        None
+    }
 }
 
 pub trait Component<B: Backend> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, spells: &Option<Vec<Spell>>);
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context);
+}
+
+pub trait StatefulComponent<B: Backend>: Stateful + Component<B> {}
+
+/// The app's state as components see it, passed to `draw` and
+/// `handle_event`. Components only read it, and ask for changes with an
+/// `Action`.
+#[derive(Default)]
+pub struct Context {
+    pub spell_query: Query,
+    pub search_results: Option<Vec<Spell>>,
+    pub item_query: ItemQuery,
+    pub item_results: Option<Vec<Item>>,
+    pub class_levels: Option<Vec<ClassLevel>>,
+    pub sources: Option<Vec<(Source, i64)>>,
+    /// The campaign sources are turned on and off for is the current one
+    pub campaigns: Vec<Campaign>,
+    pub imports: Option<Vec<Import>>,
+    /// The spell picked in the spell view, as of the last event. See
+    /// `Stateful::current_spell`.
+    pub spell: Option<Spell>,
+    /// Shown at the bottom until the next key
+    pub status: Option<String>,
+    /// Names of the favorite spells
+    pub favorites: HashSet<String>,
+    /// Terms highlighted on spell cards
+    pub glossary: Vec<GlossaryEntry>,
+}
+
+/// How much `<` and `>` resize a pane by, in percent.
+const RESIZE_STEP: i16 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    Spells,
+    Items,
+    Classes,
+    Sources,
+    About,
+}
+
+impl View {
+    const ALL: [View; 5] = [View::Spells, View::Items, View::Classes, View::Sources, View::About];
+
+    /// The tab title, led by the key that opens the view.
+    fn title(self) -> String {
+        let keys = &config::get().keys;
+        let (key, name) = match self {
+            View::Spells => (keys.spells, "Spells"),
+            View::Items => (keys.items, "Items"),
+            View::Classes => (keys.classes, "Classes"),
+            View::Sources => (keys.sources, "Sources"),
+            View::About => (keys.about, "About data"),
+        };
+        format!("{} {}", key, name)
+    }
 }
 
-pub trait StatefulComponent<B: Backend>: Stateful + Component<B>
-{}
+/// An import running on a worker thread, which reports back through
+/// `ImportEvent`s.
+pub struct ImportJob {
+    cancel: Arc<AtomicBool>,
+    pub progress: Option<Progress>,
+    pub failed: Option<String>,
+}
+
+/// The message a panic was raised with, if it has one.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("The import stopped unexpectedly"))
+}
+
+/// Asks where to write spells being exported, see `App::start_export`.
+pub struct ExportPrompt {
+    pub spells: Vec<Spell>,
+    pub title: String,
+    pub format: Format,
+    pub path: String,
+}
 
 pub struct App<'a, B: Backend> {
     pub title: &'a str,
-    pub component_tree: Container<SpellSearch, B>,
-    pub search_results: Option<Vec<Spell>>,
+    pub view: View,
+    pub context: Context,
+    pub component_tree: Container<B>,
+    pub item_tree: Container<B>,
+    pub class_tree: Container<B>,
+    pub source_list: SourceList,
+    pub about: AboutData,
+    pub import: Option<ImportJob>,
+    pub export: Option<ExportPrompt>,
+    /// Text waiting to be written to the terminal's clipboard, which
+    /// `main` does since it owns the terminal.
+    pub clipboard: Option<String>,
     pub should_quit: bool,
-    pub spell_query: Query,
-    pub conn: SqliteConnection
+    pub conn: SqliteConnection,
+    events: Sender<Event<Input>>,
 }
 
 impl<'a, B: 'static + Backend> App<'a, B> {
-    pub fn new(title: &'a str) -> App<'a, B> {
+    pub fn new(title: &'a str, conn: SqliteConnection, events: Sender<Event<Input>>) -> App<'a, B> {
+        let layouts = &config::get().layouts;
+        let sizes = load_pane_sizes(&conn);
         let mut app = App {
             title,
-            search_results: None,
-            component_tree: build_component_tree(),
+            view: View::Spells,
+            context: Context {
+                favorites: load_favorites(&conn).into_iter().collect(),
+                glossary: load_glossary(&conn),
+                ..Context::default()
+            },
+            component_tree: build_component_tree(&layouts.spells, &sizes),
+            item_tree: build_item_tree(&layouts.items, &sizes),
+            class_tree: build_class_tree(&layouts.classes, &sizes),
+            source_list: SourceList::new(),
+            about: AboutData::new(),
+            import: None,
+            export: None,
+            clipboard: None,
             should_quit: false,
-            spell_query: Query::default(),
-            conn: establish_connection()
+            conn,
+            events,
         };
         app.component_tree.hover(true);
+        app.item_tree.hover(true);
+        app.class_tree.hover(true);
+        app.source_list.hover(true);
+        app.about.hover(true);
+        app.load_sources();
+        if is_empty(&app.conn) {
+            app.start_import();
+        }
         app
     }
 
-    pub fn on_up(&mut self) {
-        self.component_tree.previous(Direction::Vertical);
+    /// Runs `build_db` on a worker thread with its own connection, since
+    /// the import would otherwise block drawing until it finished.
+    fn start_import(&mut self) {
+        if self.import.is_some() {
+            return;
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        let events = self.events.clone();
+        let cancelled = cancel.clone();
+        thread::spawn(move || {
+            // A panic, such as a database error, fails the import instead of
+            // leaving it running forever
+            let import = panic::catch_unwind(AssertUnwindSafe(|| {
+                let config = config::get();
+                match connect(&config.database) {
+                    Ok(conn) => {
+                        let result = build_db(&conn, &config.imports, &mut |progress| {
+                            events.send(Event::Import(ImportEvent::Progress(progress))).is_ok()
+                                && !cancelled.load(Ordering::Relaxed)
+                        });
+                        match result {
+                            Ok(()) => ImportEvent::Finished,
+                            Err(ImportError::Cancelled) => ImportEvent::Cancelled,
+                            Err(error) => ImportEvent::Failed(error.to_string()),
+                        }
+                    },
+                    Err(error) => ImportEvent::Failed(error),
+                }
+            }));
+            let event = import.unwrap_or_else(|panic| ImportEvent::Failed(panic_message(&*panic)));
+            events.send(Event::Import(event)).ok();
+        });
+        self.import = Some(ImportJob {
+            cancel,
+            progress: None,
+            failed: None,
+        });
+    }
+
+    pub fn on_import(&mut self, event: ImportEvent) {
+        match event {
+            ImportEvent::Progress(progress) => {
+                if let Some(job) = self.import.as_mut() {
+                    job.progress = Some(progress);
+                }
+            },
+            ImportEvent::Finished => {
+                self.import = None;
+                // The spells may all have changed, so the search starts over
+                self.component_tree = build_component_tree(
+                    &config::get().layouts.spells,
+                    &load_pane_sizes(&self.conn)
+                );
+                self.component_tree.hover(true);
+                self.context.spell_query = Query::default();
+                self.context.search_results = None;
+                self.context.spell = None;
+                self.context.favorites = load_favorites(&self.conn).into_iter().collect();
+                self.context.glossary = load_glossary(&self.conn);
+                self.load_sources();
+            },
+            ImportEvent::Cancelled => {
+                self.import = None;
+            },
+            ImportEvent::Failed(error) => {
+                if let Some(job) = self.import.as_mut() {
+                    job.failed = Some(error);
+                }
+            },
+        }
+    }
+
+    fn load_sources(&mut self) {
+        self.context.imports = Some(load_imports(&self.conn));
+        self.context.campaigns = load_campaigns(&self.conn);
+        self.context.sources = Some(
+            load_sources(&self.conn)
+                .into_iter()
+                .map(|source| {
+                    let count = count_spells(&source, &self.conn);
+                    (source, count)
+                })
+                .collect()
+        );
     }
 
-    pub fn on_down(&mut self) {
-        self.component_tree.next(Direction::Vertical);
+    fn active_tree(&mut self) -> &mut dyn Stateful {
+        match self.view {
+            View::Spells => &mut self.component_tree,
+            View::Items => &mut self.item_tree,
+            View::Classes => &mut self.class_tree,
+            View::Sources => &mut self.source_list,
+            View::About => &mut self.about,
+        }
     }
 
-    pub fn on_left(&mut self) {
-        self.component_tree.previous(Direction::Horizontal);
+    /// Offers an event to the active view's tree, see `Stateful::handle_event`.
+    fn offer(&mut self, event: &Event<Input>) -> Option<Action> {
+        let context = &self.context;
+        match self.view {
+            View::Spells => self.component_tree.handle_event(event, context),
+            View::Items => self.item_tree.handle_event(event, context),
+            View::Classes => self.class_tree.handle_event(event, context),
+            View::Sources => self.source_list.handle_event(event, context),
+            View::About => self.about.handle_event(event, context),
+        }
     }
 
-    pub fn on_right(&mut self) {
-        self.component_tree.next(Direction::Horizontal);
+    /// Exports the spell on the open card, or every result when no card is
+    /// open.
+    fn start_export(&mut self) {
+        let (spells, title) = match self.component_tree.open_spell() {
+            Some(spell) => (vec![spell.clone()], spell.name.clone()),
+            None => match &self.context.search_results {
+                Some(spells) if !spells.is_empty() => (spells.clone(), String::from("Spells")),
+                _ => {
+                    self.context.status = Some(String::from("No spells to export"));
+                    return;
+                },
+            },
+        };
+        let format = Format::Markdown;
+        self.export = Some(ExportPrompt {
+            path: export::file_name(&title, format),
+            spells,
+            title,
+            format,
+        });
     }
 
-    pub fn on_unselect(&mut self) {
-        self.component_tree.select(false);
+    /// Copies the current spell's text, or only its name.
+    fn copy(&mut self, name_only: bool) {
+        let spell = match &self.context.spell {
+            Some(spell) => spell,
+            None => {
+                self.context.status = Some(String::from("No spell to copy"));
+                return;
+            },
+        };
+        self.clipboard = Some(if name_only {
+            spell.name.clone()
+        } else {
+            export::spell(spell, Format::Markdown)
+        });
+        self.context.status = Some(format!(
+            "Copied {}{} to the clipboard",
+            spell.name,
+            if name_only { "'s name" } else { "" }
+        ));
     }
 
-    pub fn on_key(&mut self, c: char) {
+    fn on_export_key(&mut self, c: char) {
+        let prompt = match self.export.as_mut() {
+            Some(prompt) => prompt,
+            None => return,
+        };
         match c {
-            'q' => {
-                self.should_quit = true;
-            }
-            '\n' => {
-                match self.component_tree.select(true) {
-                    SelectResponse::Class(class) => {
-                        self.spell_query.class = Some(class);
-                        self.search_results = 
-                            Some(query_spell(&self.spell_query, &self.conn));
-                    },
-                    SelectResponse::Level(level) => {
-                        self.spell_query.level = Some(level);
-                        self.search_results = 
-                            Some(query_spell(&self.spell_query, &self.conn));
-                    },
-                    SelectResponse::School(school) => {
-                        self.spell_query.school = Some(school);
-                        self.search_results = 
-                            Some(query_spell(&self.spell_query, &self.conn));
-                    },
-                    _ => {}
+            '\t' => {
+                let extension = format!(".{}", prompt.format.extension());
+                prompt.format = prompt.format.next();
+                if let Some(stem) = prompt.path.strip_suffix(&extension) {
+                    prompt.path = format!("{}.{}", stem, prompt.format.extension());
                 }
             },
-            'x' => {
-                self.spell_query = Query::default();
-                self.search_results = 
-                    Some(query_spell(&self.spell_query, &self.conn));
+            '\n' => {
+                let contents = export::export(
+                    &prompt.spells,
+                    &prompt.title,
+                    &self.context.spell_query,
+                    prompt.format
+                );
+                self.context.status = Some(match std::fs::write(&prompt.path, contents) {
+                    Ok(()) => format!(
+                        "Exported {} spell(s) to {}",
+                        prompt.spells.len(),
+                        prompt.path
+                    ),
+                    Err(error) => format!("Failed to write {}: {}", prompt.path, error),
+                });
+                self.export = None;
+            },
+            c => prompt.path.push(c),
+        }
+    }
+
+    pub fn draw(&mut self, f: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ].as_ref())
+            .split(f.size());
+
+        let tabs = Tabs::new(View::ALL.iter().map(|view| Spans::from(view.title())).collect())
+            .block(Block::default().title(self.title).borders(Borders::ALL))
+            .select(View::ALL.iter().position(|view| *view == self.view).unwrap_or(0))
+            .highlight_style(Style::default().fg(config::theme().selected).add_modifier(Modifier::BOLD));
+        f.render_widget(tabs, chunks[0]);
+
+        match self.view {
+            View::Spells => self.component_tree.draw(f, chunks[1], &self.context),
+            View::Items => self.item_tree.draw(f, chunks[1], &self.context),
+            View::Classes => self.class_tree.draw(f, chunks[1], &self.context),
+            View::Sources => self.source_list.draw(f, chunks[1], &self.context),
+            View::About => self.about.draw(f, chunks[1], &self.context),
+        }
+
+        if let Some(job) = &self.import {
+            import_popup(f, chunks[1], job);
+        }
+        if let Some(prompt) = &self.export {
+            export_popup(f, chunks[1], prompt);
+        }
+        if let Some(status) = &self.context.status {
+            f.render_widget(Paragraph::new(status.as_str()), chunks[2]);
+        }
+    }
+
+    pub fn on_event(&mut self, event: Event<Input>) {
+        self.handle_event(event);
+        self.context.spell = self.component_tree.current_spell().cloned();
+    }
+
+    fn handle_event(&mut self, event: Event<Input>) {
+        let input = match event {
+            Event::Input(input) => input,
+            Event::Import(event) => return self.on_import(event),
+            Event::Tick => return,
+        };
+        // Popups only take keys
+        let key = match &input {
+            Input::Key(key) => Some(*key),
+            _ if self.import.is_some() || self.export.is_some() => return,
+            _ => None,
+        };
+        let binding = match key {
+            Some(Key::Char(c)) => config::get().keys.binding(c),
+            _ => None,
+        };
+
+        // The database is being rewritten while an import runs. Escape
+        // cancels it, or dismisses one that failed.
+        if let Some(job) = &self.import {
+            match (key, binding) {
+                (_, Some(Binding::Quit)) => self.should_quit = true,
+                (Some(Key::Esc), _) if job.failed.is_some() => self.import = None,
+                (Some(Key::Esc), _) => job.cancel.store(true, Ordering::Relaxed),
+                _ => {}
+            }
+            return;
+        }
+        if let Some(prompt) = self.export.as_mut() {
+            match key {
+                Some(Key::Esc) => self.export = None,
+                Some(Key::Backspace) => {
+                    prompt.path.pop();
+                },
+                Some(Key::Char(c)) => self.on_export_key(c),
+                _ => {}
+            }
+            return;
+        }
+
+        self.context.status = None;
+        // Tab moves focus even out of a pane that takes text
+        match key {
+            Some(Key::Char('\t')) => return self.cycle_focus(true),
+            Some(Key::BackTab) => return self.cycle_focus(false),
+            _ => {}
+        }
+        // The focused component gets the first look, so keys it doesn't
+        // use fall through to the app's bindings
+        if let Some(action) = self.offer(&Event::Input(input)) {
+            self.perform(action);
+            return;
+        }
+        match binding {
+            Some(Binding::Quit) => self.should_quit = true,
+            Some(Binding::Spells) => self.view = View::Spells,
+            Some(Binding::Items) => self.view = View::Items,
+            Some(Binding::Classes) => self.view = View::Classes,
+            Some(Binding::Sources) => self.view = View::Sources,
+            Some(Binding::About) => self.view = View::About,
+            Some(Binding::Clear) => match self.view {
+                View::Spells => {
+                    self.context.spell_query = Query::default();
+                    self.context.search_results =
+                        Some(query_spell(&self.context.spell_query, &self.conn));
+                },
+                View::Items => {
+                    self.context.item_query = ItemQuery::default();
+                    self.context.item_results =
+                        Some(query_items(&self.context.item_query, &self.conn));
+                },
+                View::Classes => {
+                    self.context.class_levels = None;
+                },
+                View::Sources | View::About => {},
+            },
+            Some(Binding::Focus(pane)) => {
+                self.active_tree().focus(pane);
             },
-            'r' => build_db(&self.conn),
+            Some(Binding::Shrink) => self.resize(-RESIZE_STEP),
+            Some(Binding::Grow) => self.resize(RESIZE_STEP),
+            Some(Binding::Import) => self.start_import(),
+            Some(Binding::Export) if self.view == View::Spells => self.start_export(),
+            Some(Binding::Copy) if self.view == View::Spells => self.copy(false),
+            Some(Binding::CopyName) if self.view == View::Spells => self.copy(true),
             _ => {}
         }
     }
+
+    /// Resizes the focused pane's column and saves its new size for the next
+    /// launch.
+    fn resize(&mut self, step: i16) {
+        match self.active_tree().resize(step) {
+            Some(sizes) => save_pane_sizes(&sizes, &self.conn),
+            None => self.context.status = Some(String::from("The focused pane can't be resized")),
+        }
+    }
+
+    /// Focuses the next pane (or the previous one), wrapping around at the
+    /// ends of the tree.
+    fn cycle_focus(&mut self, forward: bool) {
+        let tree = self.active_tree();
+        let moved = if forward { tree.focus_next() } else { tree.focus_previous() };
+        if !moved {
+            tree.hover(false);
+            if forward { tree.focus_first() } else { tree.focus_last() }
+            tree.hover(true);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Consumed => {},
+            Action::Class(class) if self.view == View::Classes => {
+                self.context.class_levels = Some(query_class_levels(class, &self.conn));
+            },
+            Action::Class(class) => {
+                self.context.spell_query.class = Some(class);
+                self.context.search_results =
+                    Some(query_spell(&self.context.spell_query, &self.conn));
+            },
+            Action::Level(level) => {
+                self.context.spell_query.level = Some(level).filter(|level| *level > 0);
+                self.context.search_results =
+                    Some(query_spell(&self.context.spell_query, &self.conn));
+            },
+            Action::CharacterLevel(level) => {
+                self.context.spell_query.character_level = Some(level).filter(|level| *level > 0);
+                self.context.search_results =
+                    Some(query_spell(&self.context.spell_query, &self.conn));
+            },
+            Action::School(school) => {
+                self.context.spell_query.school = Some(school);
+                self.context.search_results =
+                    Some(query_spell(&self.context.spell_query, &self.conn));
+            },
+            Action::Search(name) => {
+                self.context.spell_query.name = Some(name).filter(|name| !name.is_empty());
+                self.context.search_results =
+                    Some(query_spell(&self.context.spell_query, &self.conn));
+            },
+            Action::Category(category) => {
+                self.context.item_query.category = Some(category);
+                self.context.item_results =
+                    Some(query_items(&self.context.item_query, &self.conn));
+            },
+            Action::Rarity(rarity) => {
+                self.context.item_query.rarity = Some(rarity);
+                self.context.item_results =
+                    Some(query_items(&self.context.item_query, &self.conn));
+            },
+            Action::Attunement(attunement) => {
+                self.context.item_query.attunement = Some(attunement);
+                self.context.item_results =
+                    Some(query_items(&self.context.item_query, &self.conn));
+            },
+            Action::Campaign(campaign_id) => {
+                set_current_campaign(campaign_id, &self.conn);
+                self.load_sources();
+                if self.context.search_results.is_some() {
+                    self.context.search_results =
+                        Some(query_spell(&self.context.spell_query, &self.conn));
+                }
+            },
+            Action::Source(source_id, active) => {
+                set_source_active(source_id, active, &self.conn);
+                self.load_sources();
+                if self.context.search_results.is_some() {
+                    self.context.search_results =
+                        Some(query_spell(&self.context.spell_query, &self.conn));
+                }
+            },
+            Action::Favorite(name, favorite) => {
+                set_favorite(&name, favorite, &self.conn);
+                if favorite {
+                    self.context.favorites.insert(name.clone());
+                } else {
+                    self.context.favorites.remove(&name);
+                }
+                self.context.status = Some(if favorite {
+                    format!("Added {} to favorites", name)
+                } else {
+                    format!("Removed {} from favorites", name)
+                });
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use termion::event::Key;
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+
+    use dnd_tui::db_utils::{build_db, connect, load_favorites, query_spell, ImportPaths, Item,
+                           MagicItem, Query};
+
+    use crate::components::Pane;
+    use crate::config;
+    use crate::events::{Event, Input};
+
+    use super::{App, Stateful, View};
+
+    fn item(name: &str, description: &str) -> Item {
+        Item::Magic(MagicItem {
+            id: 0,
+            name: String::from(name),
+            category: String::from("wondrous items"),
+            rarity: String::from("rare"),
+            attunement: false,
+            description: String::from(description),
+        })
+    }
+
+    /// An app on the bundled SRD with every setting at its default.
+    fn app() -> App<'static, TestBackend> {
+        config::init_default();
+        let conn = connect(":memory:").unwrap();
+        build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+        App::new("test", conn, mpsc::channel().0)
+    }
+
+    fn screen(terminal: &mut Terminal<TestBackend>, app: &mut App<TestBackend>) -> String {
+        terminal.draw(|f| app.draw(f)).unwrap();
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol.as_str()).collect()
+    }
+
+    fn press(app: &mut App<TestBackend>, key: Key) {
+        app.on_event(Event::Input(Input::Key(key)));
+    }
+
+    #[test]
+    fn tab_closes_an_item_card_before_the_results_shrink() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut app = app();
+        app.view = View::Items;
+        app.context.item_results = Some(vec![
+            item("Bag of Holding", "A bag."),
+            item("Decanter of Endless Water", "A stoppered flask."),
+            item("Wand of Wonder", "A wand of unpredictable magic."),
+        ]);
+        screen(&mut terminal, &mut app);
+
+        app.item_tree.focus(Pane::Results);
+        for _ in 0..3 {
+            press(&mut app, Key::Down);
+        }
+        press(&mut app, Key::Char('\n'));
+        assert!(screen(&mut terminal, &mut app).contains("unpredictable magic"));
+
+        press(&mut app, Key::Char('\t'));
+        assert!(!screen(&mut terminal, &mut app).contains("unpredictable magic"));
+
+        app.context.item_results = Some(vec![item("Bag of Holding", "A bag.")]);
+        let screen = screen(&mut terminal, &mut app);
+        assert!(screen.contains("Bag of Holding"));
+        assert!(!screen.contains("unpredictable magic"));
+    }
+
+    #[test]
+    fn the_favorite_key_toggles_the_picked_spell() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut app = app();
+        app.context.search_results = Some(query_spell(&Query::default(), &app.conn));
+        screen(&mut terminal, &mut app);
+
+        app.component_tree.focus(Pane::Results);
+        press(&mut app, Key::Down);
+        let name = app.context.spell.as_ref().map(|spell| spell.name.clone()).unwrap();
+
+        press(&mut app, Key::Char(config::get().keys.favorite));
+        assert!(app.context.favorites.contains(&name));
+        assert_eq!(load_favorites(&app.conn), std::slice::from_ref(&name));
+        assert!(screen(&mut terminal, &mut app).contains(&format!("★ {}", name)));
+
+        press(&mut app, Key::Char(config::get().keys.favorite));
+        assert!(load_favorites(&app.conn).is_empty());
+    }
 }
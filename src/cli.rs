@@ -0,0 +1,452 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command as Process, Stdio};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use diesel::sqlite::SqliteConnection;
+
+use crate::ansi;
+use crate::components_ui::spell_card;
+use crate::config::{self, Config};
+use dnd_tui::db_utils::{self, add_campaign, build_db, find_campaign, find_spell, import_file,
+                      is_empty, load_campaigns, load_glossary, load_imports, query_spell,
+                      remove_campaign, set_current_campaign, ImportMode, Query, Spell};
+use dnd_tui::importers::SpellFormat;
+use crate::server;
+use crate::export::{self, Format};
+use dnd_tui::models::{Class, School};
+
+/// A terminal browser for D&D 5e spells, items and rules. Runs the TUI when
+/// no command is given.
+#[derive(Parser)]
+#[command(name = "dnd_tui", version)]
+pub struct Cli {
+    /// Read settings from this file instead of
+    /// ~/.config/dnd-tui/config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// The SQLite database to use
+    #[arg(long, global = true, value_name = "PATH")]
+    pub database: Option<String>,
+    /// Where the TUI writes its log
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<String>,
+    /// off, error, warn, info, debug or trace
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Search for spells and print the results
+    Search(SearchArgs),
+    /// Print a spell's card
+    Show(ShowArgs),
+    /// Import spells from a file, or rerun the configured import without
+    /// `--source`
+    Import(ImportArgs),
+    /// Serve spell lookups as a JSON API on localhost
+    Serve(ServeArgs),
+    /// Inspect the settings
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// List, add or switch campaigns, which each allow their own sources
+    #[command(subcommand)]
+    Campaign(CampaignCommand),
+}
+
+#[derive(Subcommand)]
+pub enum CampaignCommand {
+    /// List the campaigns, marking the current one
+    List,
+    /// Add a campaign that allows every source
+    Add {
+        name: String,
+    },
+    /// Make a campaign current, so its sources are the ones searched
+    Use {
+        name: String,
+    },
+    /// Remove a campaign other than the current one
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Check the config file and print every setting and where it came from
+    Check,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Output {
+    /// One line per spell with its level, school and casting details
+    Table,
+    /// Only the spell names
+    Names,
+    /// The spells and the query as JSON, like the JSON export
+    Json,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Only spells on this class's list
+    #[arg(long, value_parser = parse_class)]
+    class: Option<Class>,
+    /// Only spells of this school of magic
+    #[arg(long, value_parser = parse_school)]
+    school: Option<School>,
+    /// Spell level, where 0 is cantrips
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..=9))]
+    level: Option<i32>,
+    /// Only spells a character of `--class` can cast at this level
+    #[arg(long, requires = "class", value_parser = clap::value_parser!(i32).range(1..=20))]
+    character_level: Option<i32>,
+    /// Part of the spell's name, matched case-insensitively
+    #[arg(long)]
+    name: Option<String>,
+    /// Only ritual spells
+    #[arg(long)]
+    ritual: bool,
+    /// Only spells that need concentration
+    #[arg(long)]
+    concentration: bool,
+    #[arg(long, value_enum, default_value_t = Output::Table)]
+    format: Output,
+}
+
+#[derive(Args)]
+pub struct ShowArgs {
+    /// The spell's name, or enough of it to pick out one spell
+    name: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Layout {
+    /// The 5e-database SRD json
+    Srd,
+    /// Spells from the Open5e API
+    Open5e,
+    /// Flat records laid out like the JSON export
+    Homebrew,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// The json file to import
+    #[arg(long, requires = "format")]
+    source: Option<String>,
+    /// How the file is laid out
+    #[arg(long, value_enum, requires = "source")]
+    format: Option<Layout>,
+    /// The source the spells are filed under. Defaults to SRD for the SRD
+    /// format and the file's name otherwise
+    #[arg(long, requires = "source")]
+    name: Option<String>,
+    /// Replace every spell from the source (the default)
+    #[arg(long, conflicts_with = "merge", requires = "source")]
+    replace: bool,
+    /// Only replace spells with the same name, keeping the rest
+    #[arg(long, requires = "source")]
+    merge: bool,
+    /// Check the file and report what would change without writing
+    #[arg(long, requires = "source")]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// The port to listen on, on 127.0.0.1
+    #[arg(long, default_value_t = 8037)]
+    port: u16,
+    /// Allow requests that change the database, like enabling sources
+    #[arg(long, requires = "allow_origin")]
+    writable: bool,
+    /// The only origin browsers may call the API from, such as
+    /// `https://my-vtt.example`. Any origin may read a read-only server
+    #[arg(long, value_name = "ORIGIN")]
+    allow_origin: Option<String>,
+}
+
+fn parse_class(class: &str) -> Result<Class, String> {
+    Class::try_from(class.to_lowercase())
+}
+
+fn parse_school(school: &str) -> Result<School, &'static str> {
+    School::try_from(school.to_lowercase())
+}
+
+impl SearchArgs {
+    fn query(&self) -> Query {
+        Query {
+            class: self.class,
+            school: self.school,
+            level: self.level,
+            character_level: self.character_level,
+            name: self.name.clone(),
+            ritual: Some(true).filter(|_| self.ritual),
+            concentration: Some(true).filter(|_| self.concentration),
+        }
+    }
+}
+
+fn table(spells: &[Spell]) -> String {
+    let rows: Vec<[String; 6]> = spells.iter()
+        .map(|spell| [
+            spell.level.to_string(),
+            spell.name.clone(),
+            spell.school.clone(),
+            spell.casting_time.clone(),
+            spell.range.clone().unwrap_or_default(),
+            format!(
+                "{}{}",
+                if spell.concentration { "C" } else { "" },
+                if spell.ritual { "R" } else { "" }
+            ),
+        ])
+        .collect();
+    let header = [
+        String::from("Lvl"),
+        String::from("Name"),
+        String::from("School"),
+        String::from("Casting time"),
+        String::from("Range"),
+        String::from("C/R"),
+    ];
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| rows.iter()
+            .chain(std::iter::once(&header))
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0))
+        .collect();
+
+    std::iter::once(&header)
+        .chain(rows.iter())
+        .map(|row| row.iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Connects to the configured database, importing into it first if it's
+/// empty, as the TUI does.
+pub fn connect() -> Result<SqliteConnection, Box<dyn Error>> {
+    let config = config::get();
+    let conn = db_utils::connect(&config.database)?;
+    if is_empty(&conn) {
+        build_db(&conn, &config.imports, &mut |_| true)
+            .map_err(|error| format!("Import failed: {}", error))?;
+    }
+    Ok(conn)
+}
+
+pub fn search(args: SearchArgs) -> Result<(), Box<dyn Error>> {
+    let conn = connect()?;
+    let query = args.query();
+    let spells = query_spell(&query, &conn);
+    let output = match args.format {
+        Output::Table => table(&spells),
+        Output::Names => spells.iter()
+            .map(|spell| spell.name.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n"),
+        Output::Json => export::export(&spells, "Search", &query, Format::Json),
+    };
+    print(&output)
+}
+
+/// Prints `output`, treating a closed pipe, as when piping into `head`, as
+/// success.
+fn print(output: &str) -> Result<(), Box<dyn Error>> {
+    match writeln!(io::stdout(), "{}", output) {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Hands `text` to `$PAGER`, or `less`, returning false if it couldn't be
+/// started.
+fn page(text: &str) -> bool {
+    let pager = env::var("PAGER").unwrap_or_else(|_| String::from("less -R"));
+    let child = Process::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .stdin(Stdio::piped())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes()).ok();
+            }
+            child.wait().is_ok()
+        },
+        Err(_) => false,
+    }
+}
+
+/// Prints the card for a spell, styled and paged when writing to a terminal
+/// and as plain text otherwise.
+pub fn show(args: ShowArgs) -> Result<(), Box<dyn Error>> {
+    let conn = connect()?;
+    let spell = find_spell(&args.name, &conn)?;
+    let lines = spell_card(&spell, &load_glossary(&conn));
+
+    let tty = termion::is_tty(&io::stdout());
+    let (width, height) = if tty {
+        termion::terminal_size()
+            .map(|(width, height)| (width as usize, height as usize))
+            .unwrap_or((80, 24))
+    } else {
+        let width = env::var("COLUMNS").ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(80);
+        (width, usize::MAX)
+    };
+
+    let text = ansi::render(&lines, width, tty);
+    if tty && text.lines().count() >= height && page(&text) {
+        return Ok(());
+    }
+    print(text.trim_end())
+}
+
+pub fn import(args: ImportArgs) -> Result<(), Box<dyn Error>> {
+    let config = config::get();
+    let conn = db_utils::connect(&config.database)?;
+    let (path, layout) = match (&args.source, args.format) {
+        (Some(path), Some(layout)) => (path, layout),
+        _ => {
+            build_db(&conn, &config.imports, &mut |_| true)?;
+            let imports = load_imports(&conn);
+            let latest = imports.first().map(|import| import.imported_at);
+            for import in imports.iter().filter(|import| Some(import.imported_at) == latest) {
+                println!("Imported {} {} from {}", import.records, import.kind, import.source_path);
+            }
+            return Ok(());
+        },
+    };
+
+    let format = match layout {
+        Layout::Srd => SpellFormat::Srd,
+        Layout::Open5e => SpellFormat::Open5e,
+        Layout::Homebrew => SpellFormat::Homebrew,
+    };
+    let source = match (&args.name, format) {
+        (Some(name), _) => name.clone(),
+        (None, SpellFormat::Srd) => String::from("SRD"),
+        (None, _) => Path::new(path)
+            .file_stem()
+            .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned()),
+    };
+    let mode = if args.merge { ImportMode::Merge } else { ImportMode::Replace };
+
+    let counts = import_file(Path::new(path), format, &source, mode, args.dry_run, &conn)?;
+    println!(
+        "{} {}: {} added, {} replaced, {} removed",
+        if args.dry_run { "Would import into" } else { "Imported into" },
+        source,
+        counts.added,
+        counts.replaced,
+        counts.removed
+    );
+    Ok(())
+}
+
+pub fn campaign(command: CampaignCommand) -> Result<(), Box<dyn Error>> {
+    let conn = connect()?;
+    match command {
+        CampaignCommand::List => {
+            let campaigns = load_campaigns(&conn)
+                .iter()
+                .map(|campaign| {
+                    format!("{} {}", if campaign.current { "*" } else { " " }, campaign.name)
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            print(&campaigns)?;
+        },
+        CampaignCommand::Add { name } => {
+            add_campaign(&name, &conn)?;
+            println!("Added campaign {}", name);
+        },
+        CampaignCommand::Use { name } => {
+            let campaign = find_campaign(&name, &conn)?;
+            set_current_campaign(campaign.id, &conn);
+            println!("Switched to campaign {}", campaign.name);
+        },
+        CampaignCommand::Remove { name } => {
+            let campaign = find_campaign(&name, &conn)?;
+            remove_campaign(&campaign, &conn)?;
+            println!("Removed campaign {}", campaign.name);
+        },
+    }
+    Ok(())
+}
+
+pub fn serve(args: ServeArgs) -> Result<(), Box<dyn Error>> {
+    let allow_origin = args.allow_origin.as_deref().unwrap_or("*");
+    server::serve(args.port, args.writable, allow_origin, connect()?)
+}
+
+/// Prints every setting and where it came from, or what's wrong with the
+/// config.
+pub fn check_config(config: Result<Config, Vec<String>>) -> Result<(), Box<dyn Error>> {
+    let config = match config {
+        Ok(config) => config,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            return Err(format!("The config has {} problem(s)", errors.len()).into());
+        },
+    };
+
+    match (&config.file, config::default_path()) {
+        (Some(file), _) => println!("Config file: {}", file.display()),
+        (None, Some(default)) => println!("Config file: none, {} doesn't exist", default.display()),
+        (None, None) => println!("Config file: none"),
+    }
+    println!();
+    let rows: Vec<[&str; 3]> = config.settings.iter()
+        .map(|setting| [
+            setting.name,
+            setting.value.as_deref().unwrap_or("not set"),
+            setting.origin.as_str(),
+        ])
+        .collect();
+    let width = |column: usize| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0);
+    let (name_width, value_width) = (width(0), width(1));
+    for [name, value, origin] in rows.iter() {
+        println!(
+            "{:<name_width$}  {:<value_width$}  {}",
+            name,
+            value,
+            origin,
+            name_width = name_width,
+            value_width = value_width
+        );
+    }
+
+    let problems = config.missing_files();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    println!();
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    Err(format!("The config has {} problem(s)", problems.len()).into())
+}
@@ -0,0 +1,14 @@
+use std::env;
+
+/// The OSC 52 escape that asks the terminal to put `text` on the system
+/// clipboard. Since it travels with the rest of the output it works over
+/// ssh too. Inside tmux the escape has to be wrapped in a passthrough
+/// sequence, or tmux swallows it.
+pub fn osc52(text: &str) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64::encode(text));
+    if env::var("TMUX").is_ok() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
@@ -1,76 +1,206 @@
-use crate::app::{MoveResponse, SelectResponse, Stateful, StatefulComponent};
-use crate::db_utils::Spell;
-use crate::models::{Class, School};
-use tui::layout::Direction;
-use tui::widgets::ListState;
+use crate::app::{Action, Context, Stateful, StatefulComponent};
+use crate::config;
+use crate::events::{Event, Input};
+use crate::filters::FilterValue;
+use dnd_tui::db_utils::{Campaign, GlossaryEntry, Item, Source, Spell};
+use tui::layout::{Constraint, Direction, Rect};
+use tui::widgets::{ListState, TableState};
 use tui::backend::Backend;
-use std::borrow::Cow;
-use std::convert::TryFrom;
-use log::info;
+use termion::event::{Key, MouseButton, MouseEvent};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-pub struct Container<C: Default, B: Backend> {
+/// Splits its area between its children, which it passes events on to. See
+/// `layout` for how the tree of containers is described.
+pub struct Container<B: Backend> {
+    /// Where the container is in its layout, like `spells.panes[1]`
+    pub name: String,
     pub children: Vec<Box<dyn StatefulComponent<B>>>,
     pub direction: Direction,
+    /// Each child's share of the area, in the same order
+    pub constraints: Vec<Constraint>,
     pub selected: usize,
-    _container: C
 }
 
-impl<C: Default, B: Backend> Container<C, B> {
-    pub fn with_items(items: Vec<Box<dyn StatefulComponent<B>>>, direction: Direction) 
-    -> Container<C, B> {
+impl<B: Backend> Container<B> {
+    pub fn with_items(
+        name: String,
+        items: Vec<(Constraint, Box<dyn StatefulComponent<B>>)>,
+        direction: Direction
+    ) -> Container<B> {
+        let (constraints, children) = items.into_iter().unzip();
         Container {
-            children: items,
+            name,
+            children,
             direction,
+            constraints,
             selected: 0,
-            _container: C::default()
         }
     }
+
+    /// The sizes of the children sized as a percentage, keyed by where they
+    /// are in the layout.
+    pub fn sizes(&self) -> Vec<(String, u16)> {
+        self.constraints
+            .iter()
+            .enumerate()
+            .filter_map(|(i, constraint)| {
+                let percent = percentage(*constraint)?;
+                Some((format!("{}.panes[{}]", self.name, i), percent))
+            })
+            .collect()
+    }
+
+    /// Applies sizes saved by earlier resizes. Resizing keeps the total, so
+    /// saved sizes that don't add up to the layout's are from a layout file
+    /// that has changed since, and are left alone.
+    pub fn restore_sizes(&mut self, saved: &HashMap<String, u16>) {
+        let sizes = self.sizes();
+        let restored: Vec<u16> = sizes.iter()
+            .filter_map(|(name, _)| saved.get(name).copied())
+            .collect();
+        let total: u16 = sizes.iter().map(|(_, percent)| percent).sum();
+        if restored.len() != sizes.len() || restored.iter().sum::<u16>() != total {
+            return;
+        }
+        let constraints = self.constraints
+            .iter_mut()
+            .filter(|constraint| percentage(**constraint).is_some());
+        for (constraint, percent) in constraints.zip(restored) {
+            *constraint = Constraint::Percentage(percent);
+        }
+    }
+}
+
+/// The smallest a pane can be resized to, in percent.
+const MIN_SIZE: i16 = 10;
+
+fn percentage(constraint: Constraint) -> Option<u16> {
+    match constraint {
+        Constraint::Percentage(percent) => Some(percent),
+        _ => None,
+    }
 }
 
-impl<C: Default, B: Backend> Stateful for Container<C, B> {
-    fn next(&mut self, direction: Direction) -> MoveResponse {
-        if self.direction != direction {
-            return self.children[self.selected].next(direction);
+impl<B: Backend> Stateful for Container<B> {
+    fn handle_event(&mut self, event: &Event<Input>, context: &Context) -> Option<Action> {
+        // Mouse buttons go to the child under the pointer, and a click
+        // focuses it while the wheel scrolls it where it is
+        if let Event::Input(Input::Mouse(MouseEvent::Press(button, x, y))) = event {
+            let i = self.children.iter().position(|child| child.contains(*x, *y))?;
+            if *button == MouseButton::Left && i != self.selected {
+                self.children[self.selected].hover(false);
+                self.selected = i;
+                self.children[self.selected].hover(true);
+            }
+            return self.children[i].handle_event(event, context);
         }
 
-        match self.children[self.selected].next(direction) {
-            MoveResponse::Sibling => {
-                if self.selected < self.children.len() - 1 {
-                    self.children[self.selected].hover(false);
-                    self.selected += 1;
-                    self.children[self.selected].hover(true);
-                    MoveResponse::None
-                } else {
-                    MoveResponse::Sibling
-                }
+        if let Some(action) = self.children[self.selected].handle_event(event, context) {
+            return Some(action);
+        }
+
+        // Arrows the focused child didn't use move focus between children,
+        // or on to this container's siblings once the edge is reached
+        let selected = match (event, &self.direction) {
+            (Event::Input(Input::Key(Key::Down)), Direction::Vertical)
+            | (Event::Input(Input::Key(Key::Right)), Direction::Horizontal) => {
+                Some(self.selected + 1).filter(|i| *i < self.children.len())
             },
-            MoveResponse::None => { MoveResponse::None }
+            (Event::Input(Input::Key(Key::Up)), Direction::Vertical)
+            | (Event::Input(Input::Key(Key::Left)), Direction::Horizontal) => self.selected.checked_sub(1),
+            _ => None,
+        }?;
+        self.children[self.selected].hover(false);
+        self.selected = selected;
+        self.children[self.selected].hover(true);
+        Some(Action::Consumed)
+    }
+
+    fn hover(&mut self, activate: bool) { self.children[self.selected].hover(activate) }
+
+    fn focus_next(&mut self) -> bool {
+        if self.children[self.selected].focus_next() {
+            return true;
         }
+        if self.selected + 1 == self.children.len() {
+            return false;
+        }
+        self.children[self.selected].hover(false);
+        self.selected += 1;
+        self.children[self.selected].focus_first();
+        self.children[self.selected].hover(true);
+        true
     }
 
-    fn previous(&mut self, direction: Direction) -> MoveResponse {
-        if self.direction != direction {
-            return self.children[self.selected].previous(direction);
+    fn focus_previous(&mut self) -> bool {
+        if self.children[self.selected].focus_previous() {
+            return true;
+        }
+        if self.selected == 0 {
+            return false;
         }
+        self.children[self.selected].hover(false);
+        self.selected -= 1;
+        self.children[self.selected].focus_last();
+        self.children[self.selected].hover(true);
+        true
+    }
 
-        match self.children[self.selected].previous(direction) {
-            MoveResponse::Sibling => {
-                if self.selected > 0 {
-                    self.children[self.selected].hover(false);
-                    self.selected -= 1;
-                    self.children[self.selected].hover(true);
-                    MoveResponse::None
-                } else {
-                    MoveResponse::Sibling
-                }
-            },
-            MoveResponse::None => { MoveResponse::None }
+    fn focus_first(&mut self) {
+        self.selected = 0;
+        self.children[self.selected].focus_first();
+    }
+
+    fn focus_last(&mut self) {
+        self.selected = self.children.len() - 1;
+        self.children[self.selected].focus_last();
+    }
+
+    fn focus(&mut self, pane: Pane) -> bool {
+        let i = match self.children.iter_mut().position(|child| child.focus(pane)) {
+            Some(i) => i,
+            None => return false,
+        };
+        if i != self.selected {
+            self.children[self.selected].hover(false);
+            self.selected = i;
         }
+        true
     }
 
-    fn hover(&mut self, activate: bool) { self.children[self.selected].hover(activate) }
-    fn select(&mut self, activate: bool) -> SelectResponse {
-        self.children[self.selected].select(activate)
+    fn contains(&self, x: u16, y: u16) -> bool {
+        self.children.iter().any(|child| child.contains(x, y))
+    }
+
+    fn resize(&mut self, step: i16) -> Option<Vec<(String, u16)>> {
+        if let Some(sizes) = self.children[self.selected].resize(step) {
+            return Some(sizes);
+        }
+        // Only columns change, so `<` and `>` always mean narrower and wider
+        if self.direction == Direction::Vertical {
+            return None;
+        }
+        let size = percentage(self.constraints[self.selected])? as i16;
+        // The space is traded with the nearest pane after this one, or the
+        // one before it for the last pane
+        let (other, other_size) = (self.selected + 1..self.constraints.len())
+            .chain((0..self.selected).rev())
+            .find_map(|i| percentage(self.constraints[i]).map(|percent| (i, percent as i16)))?;
+        // Neither pane shrinks below the minimum, or further once a layout
+        // has made it smaller than that
+        let step = step.clamp((MIN_SIZE - size).min(0), (other_size - MIN_SIZE).max(0));
+        self.constraints[self.selected] = Constraint::Percentage((size + step) as u16);
+        self.constraints[other] = Constraint::Percentage((other_size - step) as u16);
+        Some(self.sizes())
+    }
+
+    fn open_spell(&self) -> Option<&Spell> {
+        self.children[self.selected].open_spell()
+    }
+
+    fn current_spell(&self) -> Option<&Spell> {
+        self.children[self.selected].current_spell()
     }
 }
 
@@ -80,82 +210,197 @@ pub enum SelectState {
     None
 }
 
-pub struct StatefulList<'a, T>
-where
-    T: Into<Cow<'static, str>>
-{
+/// Panes that keys can jump to, see `Stateful::focus`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pane {
+    Search,
+    Class,
+    School,
+    Level,
+    Results,
+}
+
+impl Pane {
+    /// The title of the pane's block.
+    fn title(self) -> &'static str {
+        match self {
+            Pane::Search => "Search",
+            Pane::Class => "Class",
+            Pane::School => "School",
+            Pane::Level => "Level",
+            Pane::Results => "Results",
+        }
+    }
+}
+
+/// Selects the pane when it's the one being jumped to.
+fn jump(selected: &mut SelectState, found: bool) -> bool {
+    if found {
+        *selected = SelectState::Selected;
+    }
+    found
+}
+
+/// The row after `i` in a list of `len` rows, wrapping around to the top.
+fn next_row(i: Option<usize>, len: usize) -> Option<usize> {
+    match i {
+        _ if len == 0 => None,
+        Some(i) if i + 1 < len => Some(i + 1),
+        _ => Some(0),
+    }
+}
+
+/// The row before `i` in a list of `len` rows, wrapping around to the
+/// bottom.
+fn previous_row(i: Option<usize>, len: usize) -> Option<usize> {
+    match i {
+        _ if len == 0 => None,
+        Some(i) if i > 0 => Some(i - 1),
+        _ => Some(len - 1),
+    }
+}
+
+/// The row the mouse wheel moves to from `i`, stopping at either end of a
+/// list of `len` rows.
+fn wheel_row(i: Option<usize>, len: usize, button: MouseButton) -> Option<usize> {
+    match (i, button) {
+        _ if len == 0 => None,
+        (Some(i), MouseButton::WheelDown) => Some((i + 1).min(len - 1)),
+        (Some(i), MouseButton::WheelUp) => Some(i.saturating_sub(1)),
+        (Some(i), _) => Some(i),
+        (None, _) => Some(0),
+    }
+}
+
+/// Lines a card scrolls for each turn of the mouse wheel.
+const SCROLL_LINES: u16 = 3;
+
+/// Longest gap between two clicks on a row for them to count as a double
+/// click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Records a click on `row`, returning whether it completes a double click.
+fn double_click(last: &mut Option<(usize, Instant)>, row: usize) -> bool {
+    let now = Instant::now();
+    let double = matches!(*last, Some((last_row, at)) if last_row == row && now - at < DOUBLE_CLICK);
+    *last = if double { None } else { Some((row, now)) };
+    double
+}
+
+/// The button and cell of a mouse press inside `area`. Wheel turns are
+/// presses too.
+fn press_in(mouse: &MouseEvent, area: Rect) -> Option<(MouseButton, u16)> {
+    match *mouse {
+        MouseEvent::Press(button, x, y) if in_rect(area, x, y) => Some((button, y)),
+        _ => None,
+    }
+}
+
+/// Whether the cell at `x`, `y` lies in `area`.
+fn in_rect(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.x && x < area.right() && y >= area.y && y < area.bottom()
+}
+
+/// Where a bordered list or table was last drawn, so clicks can be turned
+/// into rows. tui keeps how far it has scrolled private, so `update` follows
+/// the rule `List` and `Table` use for one-line rows.
+#[derive(Default)]
+pub struct ListArea {
+    pub rect: Rect,
+    header: u16,
+    offset: usize,
+}
+
+impl ListArea {
+    /// Called with each draw. `header` is the number of lines between the
+    /// top border and the first row.
+    pub fn update(&mut self, rect: Rect, header: u16, selected: Option<usize>) {
+        self.rect = rect;
+        self.header = header;
+        let height = rect.height.saturating_sub(2 + header) as usize;
+        match selected {
+            None => self.offset = 0,
+            Some(i) if i < self.offset => self.offset = i,
+            Some(i) if height > 0 && i >= self.offset + height => self.offset = i + 1 - height,
+            Some(_) => {},
+        }
+    }
+
+    /// The row of a list of `len` drawn on line `y`, if there is one.
+    fn row(&self, y: u16, len: usize) -> Option<usize> {
+        let top = self.rect.y + 1 + self.header;
+        if y < top || y + 1 >= self.rect.bottom() {
+            return None;
+        }
+        Some((y - top) as usize + self.offset).filter(|row| *row < len)
+    }
+}
+
+/// A list of filter values, which sends the picked value's action to the app.
+pub struct StatefulList<'a, T: FilterValue> {
     pub state: ListState,
     pub items: Vec<T>,
     pub name: &'a str,
     pub selected: SelectState,
+    pub area: ListArea,
 }
 
-impl<'a, T> StatefulList<'a, T>
-where
-    T: Into<Cow<'static, str>> + Into<String>
-{
+impl<'a, T: FilterValue> StatefulList<'a, T> {
     pub fn with_items(items: Vec<T>, name: &'a str) -> StatefulList<'a, T> {
         StatefulList {
             state: ListState::default(),
             items,
             name,
             selected: SelectState::None,
+            area: ListArea::default(),
         }
     }
-}
 
-impl<'a, T> Stateful for StatefulList<'a, T>
-where
-    T: Into<Cow<'static, str>> + Into<String> + Clone
-{
-    fn next(&mut self, direction: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
-                match direction {
-                    Direction::Horizontal => MoveResponse::None,
-                    Direction::Vertical => {
-                        let i = match self.state.selected() {
-                            Some(i) => {
-                                if i >= self.items.len() - 1 {
-                                    0
-                                } else {
-                                    i + 1
-                                }
-                            },
-                            None => 0,
-                        };
-                        self.state.select(Some(i));
-                        MoveResponse::None
-                    }
+    /// A click picks the item under it, like Enter.
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        let (button, y) = press_in(mouse, self.area.rect)?;
+        match button {
+            MouseButton::Left => {
+                self.selected = SelectState::Selected;
+                if let Some(row) = self.area.row(y, self.items.len()) {
+                    self.state.select(Some(row));
+                    return Some(self.items[row].action());
                 }
             },
-            _ => MoveResponse::Sibling,
-        }
-    }
-
-    fn previous(&mut self, direction: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
-                match direction {
-                    Direction::Horizontal => MoveResponse::None,
-                    Direction::Vertical => {
-                        let i = match self.state.selected() {
-                            Some(i) => {
-                                if i <= 0 {
-                                    self.items.len() - 1
-                                } else {
-                                    i - 1
-                                }
-                            },
-                            None => 0,
-                        };
-                        self.state.select(Some(i));
-                        MoveResponse::None
-                    }
-                }
+            MouseButton::WheelUp | MouseButton::WheelDown => {
+                self.state.select(wheel_row(self.state.selected(), self.items.len(), button));
+            },
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+}
+
+impl<'a, T: FilterValue> Stateful for StatefulList<'a, T> {
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down) => {
+                self.state.select(next_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Up) => {
+                self.state.select(previous_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Left | Key::Right) => {},
+            (SelectState::Selected, Key::Char('\n')) => {
+                self.selected = SelectState::Highlighted;
+                return Some(self.state.selected()
+                    .map_or(Action::Consumed, |i| self.items[i].action()));
             },
-            _ => MoveResponse::Sibling,
+            (SelectState::Selected, Key::Esc) => self.selected = SelectState::Highlighted,
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
         }
+        Some(Action::Consumed)
     }
 
     fn hover(&mut self, activate: bool) {
@@ -166,50 +411,54 @@ where
         }
     }
 
-    fn select(&mut self, activate: bool) -> SelectResponse {
-        if activate {
-            match self.selected {
-                SelectState::Selected => {
-                    self.selected = SelectState::Highlighted;
-                    if let Some(i) = self.state.selected() {
-                        match self.name {
-                            "Class" => {
-                                SelectResponse::Class(
-                                    Class::try_from(
-                                        Into::<String>::into(self.items[i].clone()).to_lowercase()
-                                    ).expect("Invalid class")
-                                )
-                            },
-                            "School" => {
-                                SelectResponse::School(
-                                    School::try_from(
-                                        Into::<String>::into(self.items[i].clone()).to_lowercase()
-                                    ).expect("Invalid school")
-                                )
-                            },
-                            _ => SelectResponse::None,
-                        }
-                    } else {
-                        SelectResponse::None
-                    }
-                },
-                _ => {
-                    self.selected = SelectState::Selected;
-                    SelectResponse::None
-                },
-            }
-        } else {
-            self.selected = SelectState::None;
-            SelectResponse::None
-        }
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area.rect, x, y) }
+
+    fn focus(&mut self, pane: Pane) -> bool {
+        jump(&mut self.selected, pane.title() == self.name)
     }
 }
 
+/// Finds whole-word, case-insensitive occurrences of glossary terms in `text`,
+/// returned as non-overlapping `(start, end, entry)` triples in text order.
+pub fn glossary_matches(text: &str, glossary: &[GlossaryEntry]) -> Vec<(usize, usize, usize)> {
+    let haystack = text.to_ascii_lowercase();
+    let bytes = haystack.as_bytes();
+    let mut matches: Vec<(usize, usize, usize)> = glossary.iter()
+        .enumerate()
+        .flat_map(|(entry, term)| {
+            let needle = term.term.to_ascii_lowercase();
+            haystack.match_indices(needle.as_str())
+                .map(|(start, found)| (start, start + found.len(), entry))
+                .filter(|(start, end, _)| {
+                    (*start == 0 || !bytes[start - 1].is_ascii_alphanumeric())
+                        && (*end == bytes.len() || !bytes[*end].is_ascii_alphanumeric())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    matches.sort_by_key(|(start, end, _)| (*start, usize::MAX - end));
+    let mut last_end = 0;
+    matches.retain(|(start, end, _)| {
+        if *start < last_end {
+            return false;
+        }
+        last_end = *end;
+        true
+    });
+    matches
+}
+
 pub struct SearchResults {
     pub state: ListState,
     pub items: Vec<Spell>,
     pub selected: SelectState,
     pub spell_card: bool,
+    pub popup: Option<usize>,
+    pub area: ListArea,
+    /// How far the open card is scrolled
+    pub scroll: u16,
+    last_click: Option<(usize, Instant)>,
 }
 
 impl SearchResults {
@@ -219,85 +468,269 @@ impl SearchResults {
             items,
             selected: SelectState::None,
             spell_card: false,
+            popup: None,
+            area: ListArea::default(),
+            scroll: 0,
+            last_click: None,
         }
     }
-}
 
-impl<'a> Stateful for SearchResults {
-    fn next(&mut self, direction: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
-                match direction {
-                    Direction::Horizontal => MoveResponse::None,
-                    Direction::Vertical => {
-                        let i = match self.state.selected() {
-                            Some(i) => {
-                                if i >= self.items.len() - 1 {
-                                    0
-                                } else {
-                                    i + 1
-                                }
-                            },
-                            None => 0,
-                        };
-                        self.state.select(Some(i));
-                        MoveResponse::None
+    /// Glossary entries mentioned on the card of the spell at `i`, in the
+    /// order they first appear.
+    pub fn card_terms<'g>(&self, i: usize, glossary: &'g [GlossaryEntry])
+        -> Vec<&'g GlossaryEntry> {
+        let spell = &self.items[i];
+        let mut terms: Vec<&GlossaryEntry> = Vec::new();
+        let text = match &spell.higher_level {
+            Some(higher_level) => format!("{}\n{}", spell.description, higher_level),
+            None => spell.description.clone(),
+        };
+        for (_, _, entry) in glossary_matches(&text, glossary) {
+            if !terms.iter().any(|term| term.id == glossary[entry].id) {
+                terms.push(&glossary[entry]);
+            }
+        }
+        terms
+    }
+
+    /// Clicking a row highlights it and double clicking opens its card,
+    /// while the wheel scrolls the list, the open card or the glossary.
+    fn on_mouse(&mut self, mouse: &MouseEvent, glossary: &[GlossaryEntry]) -> Option<Action> {
+        let (button, y) = press_in(mouse, self.area.rect)?;
+        if self.popup.is_some() {
+            match button {
+                MouseButton::WheelDown => self.cycle_popup(true, glossary),
+                MouseButton::WheelUp => self.cycle_popup(false, glossary),
+                _ => self.popup = None,
+            }
+            return Some(Action::Consumed);
+        }
+
+        match (button, self.spell_card) {
+            (MouseButton::WheelDown, true) => self.scroll = self.scroll.saturating_add(SCROLL_LINES),
+            (MouseButton::WheelUp, true) => self.scroll = self.scroll.saturating_sub(SCROLL_LINES),
+            (MouseButton::WheelUp | MouseButton::WheelDown, false) => {
+                self.state.select(wheel_row(self.state.selected(), self.items.len(), button));
+            },
+            (MouseButton::Left, true) => self.selected = SelectState::Selected,
+            (MouseButton::Left, false) => {
+                self.selected = SelectState::Selected;
+                if let Some(row) = self.area.row(y, self.items.len()) {
+                    self.state.select(Some(row));
+                    if double_click(&mut self.last_click, row) {
+                        self.spell_card = true;
+                        self.scroll = 0;
                     }
                 }
             },
-            _ => MoveResponse::Sibling,
-        }
-    }
-
-    fn previous(&mut self, direction: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
-                match direction {
-                    Direction::Horizontal => MoveResponse::None,
-                    Direction::Vertical => {
-                        let i = match self.state.selected() {
-                            Some(i) => {
-                                if i <= 0 {
-                                    self.items.len() - 1
-                                } else {
-                                    i - 1
-                                }
-                            },
-                            None => 0,
-                        };
-                        self.state.select(Some(i));
-                        MoveResponse::None
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+
+    fn cycle_popup(&mut self, forward: bool, glossary: &[GlossaryEntry]) {
+        if let (Some(popup), Some(i)) = (self.popup, self.state.selected()) {
+            let count = self.card_terms(i, glossary).len();
+            self.popup = Some(if forward {
+                (popup + 1) % count
+            } else {
+                (popup + count - 1) % count
+            });
+        }
+    }
+}
+
+impl Stateful for SearchResults {
+    fn handle_event(&mut self, event: &Event<Input>, context: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse, &context.glossary),
+            _ => return None,
+        };
+        if self.popup.is_some() {
+            match key {
+                Key::Down | Key::Right => self.cycle_popup(true, &context.glossary),
+                Key::Up | Key::Left => self.cycle_popup(false, &context.glossary),
+                Key::Esc => self.popup = None,
+                _ => return None,
+            }
+            return Some(Action::Consumed);
+        }
+
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down) => {
+                self.state.select(next_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Up) => {
+                self.state.select(previous_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Left | Key::Right) => {},
+            (SelectState::Selected, Key::Char('\n')) => {
+                // Selecting an open card brings up the glossary for the
+                // terms it mentions
+                if self.spell_card {
+                    if let Some(i) = self.state.selected() {
+                        if !self.card_terms(i, &context.glossary).is_empty() {
+                            self.popup = Some(0);
+                        }
                     }
                 }
+                if !self.spell_card {
+                    self.scroll = 0;
+                }
+                self.spell_card = self.state.selected().is_some();
+            },
+            (SelectState::Selected, Key::Esc) => {
+                self.spell_card = false;
+                self.selected = SelectState::Highlighted;
             },
-            _ => MoveResponse::Sibling,
+            (SelectState::Selected, Key::Char(c)) if *c == config::get().keys.favorite => {
+                let name = self.current_spell()?.name.clone();
+                let favorite = !context.favorites.contains(&name);
+                return Some(Action::Favorite(name, favorite));
+            },
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
         }
+        Some(Action::Consumed)
     }
 
+    /// Leaving the results closes the card, as the filters may then change
+    /// the spells under it.
     fn hover(&mut self, activate: bool) {
         if activate {
             self.selected = SelectState::Highlighted;
         } else {
             self.selected = SelectState::None;
+            self.spell_card = false;
+            self.popup = None;
+            self.scroll = 0;
+            if self.state.selected().is_some_and(|i| i >= self.items.len()) {
+                self.state.select(self.items.len().checked_sub(1));
+            }
+        }
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area.rect, x, y) }
+
+    fn focus(&mut self, pane: Pane) -> bool {
+        jump(&mut self.selected, pane == Pane::Results)
+    }
+
+    fn open_spell(&self) -> Option<&Spell> {
+        match (self.spell_card, self.state.selected()) {
+            (true, Some(i)) => self.items.get(i),
+            _ => None,
+        }
+    }
+
+    fn current_spell(&self) -> Option<&Spell> {
+        match (&self.selected, self.state.selected()) {
+            (SelectState::Selected, Some(i)) => self.items.get(i),
+            _ => None,
+        }
+    }
+}
+
+pub struct ItemResults {
+    pub state: ListState,
+    pub items: Vec<Item>,
+    pub selected: SelectState,
+    pub item_card: bool,
+    pub area: ListArea,
+    /// How far the open card is scrolled
+    pub scroll: u16,
+    last_click: Option<(usize, Instant)>,
+}
+
+impl ItemResults {
+    pub fn with_items(items: Vec<Item>) -> ItemResults {
+        ItemResults {
+            state: ListState::default(),
+            items,
+            selected: SelectState::None,
+            item_card: false,
+            area: ListArea::default(),
+            scroll: 0,
+            last_click: None,
+        }
+    }
+
+    /// Works like the spell results, see `SearchResults::on_mouse`.
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        let (button, y) = press_in(mouse, self.area.rect)?;
+        match (button, self.item_card) {
+            (MouseButton::WheelDown, true) => self.scroll = self.scroll.saturating_add(SCROLL_LINES),
+            (MouseButton::WheelUp, true) => self.scroll = self.scroll.saturating_sub(SCROLL_LINES),
+            (MouseButton::WheelUp | MouseButton::WheelDown, false) => {
+                self.state.select(wheel_row(self.state.selected(), self.items.len(), button));
+            },
+            (MouseButton::Left, true) => self.selected = SelectState::Selected,
+            (MouseButton::Left, false) => {
+                self.selected = SelectState::Selected;
+                if let Some(row) = self.area.row(y, self.items.len()) {
+                    self.state.select(Some(row));
+                    if double_click(&mut self.last_click, row) {
+                        self.item_card = true;
+                        self.scroll = 0;
+                    }
+                }
+            },
+            _ => return None,
         }
+        Some(Action::Consumed)
+    }
+}
+
+impl Stateful for ItemResults {
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down) => {
+                self.state.select(next_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Up) => {
+                self.state.select(previous_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Left | Key::Right) => {},
+            (SelectState::Selected, Key::Char('\n')) => {
+                if !self.item_card {
+                    self.scroll = 0;
+                }
+                self.item_card = self.state.selected().is_some_and(|i| i < self.items.len());
+            },
+            (SelectState::Selected, Key::Esc) => {
+                self.item_card = false;
+                self.selected = SelectState::Highlighted;
+            },
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
+        }
+        Some(Action::Consumed)
     }
 
-    fn select(&mut self, activate: bool) -> SelectResponse {
+    /// Leaving the results closes the card, as the filters may then change
+    /// the items under it.
+    fn hover(&mut self, activate: bool) {
         if activate {
-            match self.selected {
-                SelectState::Selected => {
-                    self.spell_card = true;
-                },
-                _ => {
-                    self.selected = SelectState::Selected;
-                },
-            }
+            self.selected = SelectState::Highlighted;
         } else {
-            self.spell_card = false;
             self.selected = SelectState::None;
+            self.item_card = false;
+            if self.state.selected().is_some_and(|i| i >= self.items.len()) {
+                self.state.select(self.items.len().checked_sub(1));
+            }
         }
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area.rect, x, y) }
 
-        SelectResponse::None
+    fn focus(&mut self, pane: Pane) -> bool {
+        jump(&mut self.selected, pane == Pane::Results)
     }
 }
 
@@ -305,6 +738,7 @@ pub struct SearchBar<'a> {
     pub name: &'a str,
     pub value: String,
     pub selected: SelectState,
+    pub area: Rect,
 }
 
 impl<'a> SearchBar<'a> {
@@ -313,22 +747,48 @@ impl<'a> SearchBar<'a> {
             name,
             value: String::new(),
             selected: SelectState::None,
+            area: Rect::default(),
         }
     }
-}
 
-impl<'a> Stateful for SearchBar<'a> {
-    fn next(&mut self, _: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => MoveResponse::None,
-            _ => MoveResponse::Sibling,
+    /// A click starts typing.
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        match press_in(mouse, self.area)? {
+            (MouseButton::Left, _) => self.selected = SelectState::Selected,
+            _ => return None,
         }
+        Some(Action::Consumed)
     }
+}
 
-    fn previous(&mut self, _: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => MoveResponse::None,
-            _ => MoveResponse::Sibling,
+impl<'a> Stateful for SearchBar<'a> {
+    /// While selected the bar takes every key as text, reporting the new
+    /// value with each change, until Enter or Esc.
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Char('\n')) | (SelectState::Selected, Key::Esc) => {
+                self.selected = SelectState::Highlighted;
+                Some(Action::Consumed)
+            },
+            (SelectState::Selected, Key::Char(c)) => {
+                self.value.push(*c);
+                Some(Action::Search(self.value.clone()))
+            },
+            (SelectState::Selected, Key::Backspace) => {
+                self.value.pop();
+                Some(Action::Search(self.value.clone()))
+            },
+            (SelectState::Selected, _) => Some(Action::Consumed),
+            (SelectState::Highlighted, Key::Char('\n')) => {
+                self.selected = SelectState::Selected;
+                Some(Action::Consumed)
+            },
+            _ => None,
         }
     }
 
@@ -340,51 +800,75 @@ impl<'a> Stateful for SearchBar<'a> {
         }
     }
 
-    fn select(&mut self, activate: bool) -> SelectResponse {
-        if activate {
-            self.selected = SelectState::Selected;
-        } else {
-            self.selected = SelectState::None;
-        }
-        SelectResponse::None
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area, x, y) }
+
+    fn focus(&mut self, pane: Pane) -> bool {
+        jump(&mut self.selected, pane.title() == self.name)
     }
 }
 
+/// A numeric picker from "Any" (0) up to `max`, which reports the picked
+/// value through `response` when confirmed.
 pub struct Level<'a> {
     pub name: &'a str,
     pub level: i32,
+    pub max: i32,
+    pub response: fn(i32) -> Action,
     pub selected: SelectState,
+    pub area: Rect,
 }
 
 impl<'a> Level<'a> {
-    pub fn new(name: &'a str) -> Level<'a> {
+    pub fn new(name: &'a str, max: i32, response: fn(i32) -> Action) -> Level<'a> {
         Level {
             name,
             level: 0,
+            max,
+            response,
             selected: SelectState::None,
+            area: Rect::default(),
         }
     }
-}
 
-impl<'a> Stateful for Level<'a> {
-    fn next(&mut self, _: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
-                if self.level < 20 { self.level += 1; }
-                MoveResponse::None
+    /// A click selects the picker and the wheel then changes its value.
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        match (press_in(mouse, self.area)?, &self.selected) {
+            ((MouseButton::Left, _), _) => self.selected = SelectState::Selected,
+            ((MouseButton::WheelDown, _), SelectState::Selected) => {
+                if self.level < self.max { self.level += 1; }
             },
-            _ => MoveResponse::Sibling,
+            ((MouseButton::WheelUp, _), SelectState::Selected) => {
+                if self.level > 0 { self.level -= 1; }
+            },
+            _ => return None,
         }
+        Some(Action::Consumed)
     }
+}
 
-    fn previous(&mut self, _: Direction) -> MoveResponse {
-        match self.selected {
-            SelectState::Selected => {
+impl<'a> Stateful for Level<'a> {
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down | Key::Right) => {
+                if self.level < self.max { self.level += 1; }
+            },
+            (SelectState::Selected, Key::Up | Key::Left) => {
                 if self.level > 0 { self.level -= 1; }
-                MoveResponse::None
             },
-            _ => MoveResponse::Sibling,
+            (SelectState::Selected, Key::Char('\n')) => {
+                self.selected = SelectState::Highlighted;
+                return Some((self.response)(self.level));
+            },
+            (SelectState::Selected, Key::Esc) => self.selected = SelectState::Highlighted,
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
         }
+        Some(Action::Consumed)
     }
 
     fn hover(&mut self, activate: bool) {
@@ -395,21 +879,442 @@ impl<'a> Stateful for Level<'a> {
         }
     }
 
-    fn select(&mut self, activate: bool) -> SelectResponse {
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area, x, y) }
+
+    fn focus(&mut self, pane: Pane) -> bool {
+        jump(&mut self.selected, pane.title() == self.name)
+    }
+}
+
+pub struct ProgressionTable {
+    pub state: TableState,
+    pub rows: usize,
+    pub selected: SelectState,
+    pub area: ListArea,
+}
+
+impl ProgressionTable {
+    pub fn new() -> ProgressionTable {
+        ProgressionTable {
+            state: TableState::default(),
+            rows: 0,
+            selected: SelectState::None,
+            area: ListArea::default(),
+        }
+    }
+
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        let (button, y) = press_in(mouse, self.area.rect)?;
+        match button {
+            MouseButton::Left => {
+                self.selected = SelectState::Selected;
+                if let Some(row) = self.area.row(y, self.rows) {
+                    self.state.select(Some(row));
+                }
+            },
+            MouseButton::WheelUp | MouseButton::WheelDown => {
+                self.state.select(wheel_row(self.state.selected(), self.rows, button));
+            },
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+}
+
+impl Stateful for ProgressionTable {
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down) => {
+                self.state.select(next_row(self.state.selected(), self.rows));
+            },
+            (SelectState::Selected, Key::Up) => {
+                self.state.select(previous_row(self.state.selected(), self.rows));
+            },
+            (SelectState::Selected, Key::Left | Key::Right | Key::Char('\n')) => {},
+            (SelectState::Selected, Key::Esc) => {
+                self.selected = SelectState::Highlighted;
+                self.state.select(None);
+            },
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+
+    fn hover(&mut self, activate: bool) {
         if activate {
-            match self.selected {
-                SelectState::Selected => {
-                    self.selected = SelectState::None;
-                    SelectResponse::Level(self.level)
-                },
-                _ => {
-                    self.selected = SelectState::Selected;
-                    SelectResponse::None
+            self.selected = SelectState::Highlighted;
+        } else {
+            self.selected = SelectState::None;
+        }
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area.rect, x, y) }
+}
+
+pub struct SourceList {
+    pub state: ListState,
+    pub items: Vec<(Source, i64)>,
+    pub selected: SelectState,
+    pub area: ListArea,
+}
+
+impl SourceList {
+    pub fn new() -> SourceList {
+        SourceList {
+            state: ListState::default(),
+            items: vec![],
+            selected: SelectState::None,
+            area: ListArea::default(),
+        }
+    }
+
+    /// Clicking a source toggles it.
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        let (button, y) = press_in(mouse, self.area.rect)?;
+        match button {
+            MouseButton::Left => {
+                self.selected = SelectState::Selected;
+                if let Some(row) = self.area.row(y, self.items.len()) {
+                    self.state.select(Some(row));
+                    let (source, _) = &self.items[row];
+                    return Some(Action::Source(source.id, !source.active));
                 }
-            }
+            },
+            MouseButton::WheelUp | MouseButton::WheelDown => {
+                self.state.select(wheel_row(self.state.selected(), self.items.len(), button));
+            },
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+}
+
+/// The campaign before or after the current one, wrapping around.
+fn cycle_campaign(campaigns: &[Campaign], forward: bool) -> Option<&Campaign> {
+    let current = campaigns.iter().position(|campaign| campaign.current)?;
+    let i = if forward {
+        next_row(Some(current), campaigns.len())
+    } else {
+        previous_row(Some(current), campaigns.len())
+    }?;
+    Some(&campaigns[i]).filter(|_| i != current)
+}
+
+impl Stateful for SourceList {
+    /// Left and right switch to another campaign, whose sources are then
+    /// shown.
+    fn handle_event(&mut self, event: &Event<Input>, context: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected | SelectState::Highlighted, Key::Left | Key::Right) => {
+                let campaign = cycle_campaign(&context.campaigns, *key == Key::Right)?;
+                return Some(Action::Campaign(campaign.id));
+            },
+            (SelectState::Selected, Key::Down) => {
+                self.state.select(next_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Up) => {
+                self.state.select(previous_row(self.state.selected(), self.items.len()));
+            },
+            (SelectState::Selected, Key::Char('\n')) => {
+                let (source, _) = self.state.selected().and_then(|i| self.items.get(i))?;
+                return Some(Action::Source(source.id, !source.active));
+            },
+            (SelectState::Selected, Key::Esc) => self.selected = SelectState::Highlighted,
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+
+    fn hover(&mut self, activate: bool) {
+        if activate {
+            self.selected = SelectState::Highlighted;
         } else {
             self.selected = SelectState::None;
-            SelectResponse::None
         }
     }
+
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area.rect, x, y) }
+}
+
+pub struct AboutData {
+    pub scroll: u16,
+    pub selected: SelectState,
+    pub area: Rect,
+}
+
+impl AboutData {
+    pub fn new() -> AboutData {
+        AboutData {
+            scroll: 0,
+            selected: SelectState::None,
+            area: Rect::default(),
+        }
+    }
+
+    fn on_mouse(&mut self, mouse: &MouseEvent) -> Option<Action> {
+        match press_in(mouse, self.area)? {
+            (MouseButton::Left, _) => self.selected = SelectState::Selected,
+            (MouseButton::WheelDown, _) => self.scroll = self.scroll.saturating_add(SCROLL_LINES),
+            (MouseButton::WheelUp, _) => self.scroll = self.scroll.saturating_sub(SCROLL_LINES),
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+}
+
+impl Stateful for AboutData {
+    fn handle_event(&mut self, event: &Event<Input>, _: &Context) -> Option<Action> {
+        let key = match event {
+            Event::Input(Input::Key(key)) => key,
+            Event::Input(Input::Mouse(mouse)) => return self.on_mouse(mouse),
+            _ => return None,
+        };
+        match (&self.selected, key) {
+            (SelectState::Selected, Key::Down) => self.scroll = self.scroll.saturating_add(1),
+            (SelectState::Selected, Key::Up) => self.scroll = self.scroll.saturating_sub(1),
+            (SelectState::Selected, Key::Left | Key::Right | Key::Char('\n')) => {},
+            (SelectState::Selected, Key::Esc) => self.selected = SelectState::Highlighted,
+            (SelectState::Highlighted, Key::Char('\n')) => self.selected = SelectState::Selected,
+            _ => return None,
+        }
+        Some(Action::Consumed)
+    }
+
+    fn hover(&mut self, activate: bool) {
+        if activate {
+            self.selected = SelectState::Highlighted;
+        } else {
+            self.selected = SelectState::None;
+        }
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool { in_rect(self.area, x, y) }
+}
+
+#[cfg(test)]
+mod tests {
+    use termion::event::Key;
+    use tui::backend::TestBackend;
+    use tui::layout::{Constraint, Direction};
+
+    use dnd_tui::db_utils::{Item, MagicItem, Spell};
+    use dnd_tui::models::Class;
+
+    use crate::app::{Action, Context};
+    use crate::events::{Event, Input};
+
+    use super::{Container, ItemResults, Level, SearchBar, SearchResults, SelectState, Stateful,
+                StatefulList};
+
+    fn press(component: &mut dyn Stateful, key: Key) -> Option<Action> {
+        component.handle_event(&Event::Input(Input::Key(key)), &Context::default())
+    }
+
+    fn item(name: &str) -> Item {
+        Item::Magic(MagicItem {
+            id: 0,
+            name: String::from(name),
+            category: String::from("wondrous items"),
+            rarity: String::from("rare"),
+            attunement: false,
+            description: String::new(),
+        })
+    }
+
+    fn spell(name: &str) -> Spell {
+        Spell {
+            name: String::from(name),
+            ..Spell::default()
+        }
+    }
+
+    #[test]
+    fn leaving_spell_results_closes_the_card() {
+        let mut results = SearchResults::with_items(vec![spell("Fireball"), spell("Fire Bolt")]);
+        results.selected = SelectState::Selected;
+        results.state.select(Some(1));
+        results.spell_card = true;
+        results.popup = Some(0);
+        results.scroll = 4;
+
+        results.hover(false);
+        assert!(results.open_spell().is_none());
+        assert_eq!((results.popup, results.scroll), (None, 0));
+        assert_eq!(results.state.selected(), Some(1));
+
+        results.items.truncate(1);
+        results.state.select(Some(1));
+        results.hover(false);
+        assert_eq!(results.state.selected(), Some(0));
+
+        results.items.clear();
+        results.hover(false);
+        assert_eq!(results.state.selected(), None);
+    }
+
+    #[test]
+    fn leaving_item_results_closes_the_card() {
+        let mut results = ItemResults::with_items(vec![item("Bag of Holding"), item("Decanter")]);
+        results.selected = SelectState::Selected;
+        results.state.select(Some(1));
+        results.item_card = true;
+
+        results.hover(false);
+        assert!(!results.item_card);
+        assert_eq!(results.state.selected(), Some(1));
+
+        results.items.truncate(1);
+        results.state.select(Some(1));
+        results.hover(false);
+        assert_eq!(results.state.selected(), Some(0));
+
+        results.items.clear();
+        results.hover(false);
+        assert_eq!(results.state.selected(), None);
+    }
+
+    fn columns(percents: &[u16]) -> Container<TestBackend> {
+        let items = percents.iter()
+            .map(|percent| {
+                let results = Box::new(ItemResults::with_items(Vec::new()));
+                (Constraint::Percentage(*percent), results as _)
+            })
+            .collect();
+        Container::with_items(String::from("items"), items, Direction::Horizontal)
+    }
+
+    fn percents(container: &Container<TestBackend>) -> Vec<u16> {
+        container.sizes().into_iter().map(|(_, percent)| percent).collect()
+    }
+
+    #[test]
+    fn resizes_columns_within_the_minimum() {
+        let mut container = columns(&[30, 70]);
+        container.resize(5);
+        assert_eq!(percents(&container), [35, 65]);
+        container.resize(-40);
+        assert_eq!(percents(&container), [10, 90]);
+        container.resize(100);
+        assert_eq!(percents(&container), [90, 10]);
+    }
+
+    #[test]
+    fn keeps_columns_below_the_minimum_from_shrinking() {
+        let mut container = columns(&[5, 3, 92]);
+        container.resize(-5);
+        assert_eq!(percents(&container), [5, 3, 92]);
+        container.resize(5);
+        assert_eq!(percents(&container), [5, 3, 92]);
+
+        // The last column trades with the one before it
+        container.selected = 2;
+        container.resize(5);
+        assert_eq!(percents(&container), [5, 3, 92]);
+        container.resize(-5);
+        assert_eq!(percents(&container), [5, 8, 87]);
+    }
+
+    #[test]
+    fn level_reports_the_picked_level() {
+        let mut level = Level::new("Level", 9, Action::Level);
+        level.hover(true);
+        assert!(matches!(press(&mut level, Key::Char('\n')), Some(Action::Consumed)));
+        for key in [Key::Right, Key::Down, Key::Down, Key::Up] {
+            assert!(matches!(press(&mut level, key), Some(Action::Consumed)));
+        }
+        assert!(matches!(press(&mut level, Key::Char('\n')), Some(Action::Level(2))));
+        assert!(matches!(level.selected, SelectState::Highlighted));
+    }
+
+    #[test]
+    fn level_stays_between_any_and_the_maximum() {
+        let mut level = Level::new("Level", 2, Action::CharacterLevel);
+        level.selected = SelectState::Selected;
+        press(&mut level, Key::Left);
+        assert_eq!(level.level, 0);
+        for _ in 0..4 {
+            press(&mut level, Key::Right);
+        }
+        assert!(matches!(press(&mut level, Key::Char('\n')), Some(Action::CharacterLevel(2))));
+    }
+
+    #[test]
+    fn search_bar_reports_the_text_as_it_changes() {
+        let mut bar = SearchBar::new("Search");
+        bar.hover(true);
+        assert!(press(&mut bar, Key::Char('f')).is_none());
+        assert!(matches!(press(&mut bar, Key::Char('\n')), Some(Action::Consumed)));
+
+        press(&mut bar, Key::Char('f'));
+        let typed = press(&mut bar, Key::Char('i'));
+        assert!(matches!(typed, Some(Action::Search(text)) if text == "fi"));
+        let erased = press(&mut bar, Key::Backspace);
+        assert!(matches!(erased, Some(Action::Search(text)) if text == "f"));
+        // Selected, the bar keeps keys the app would otherwise act on
+        assert!(matches!(press(&mut bar, Key::Down), Some(Action::Consumed)));
+    }
+
+    #[test]
+    fn esc_hands_the_keys_back() {
+        let mut bar = SearchBar::new("Search");
+        bar.selected = SelectState::Selected;
+        assert!(matches!(press(&mut bar, Key::Esc), Some(Action::Consumed)));
+        assert!(press(&mut bar, Key::Char('q')).is_none());
+
+        let mut list = StatefulList::with_items(vec![Class::Bard, Class::Wizard], "Class");
+        list.selected = SelectState::Selected;
+        assert!(matches!(press(&mut list, Key::Esc), Some(Action::Consumed)));
+        assert!(press(&mut list, Key::Down).is_none());
+    }
+
+    #[test]
+    fn list_reports_the_picked_value() {
+        let mut list = StatefulList::with_items(vec![Class::Bard, Class::Wizard], "Class");
+        list.hover(true);
+        press(&mut list, Key::Char('\n'));
+        press(&mut list, Key::Down);
+        press(&mut list, Key::Down);
+        assert!(matches!(press(&mut list, Key::Char('\n')), Some(Action::Class(Class::Wizard))));
+
+        // Down from the bottom wraps around to the top
+        press(&mut list, Key::Char('\n'));
+        press(&mut list, Key::Down);
+        assert!(matches!(press(&mut list, Key::Char('\n')), Some(Action::Class(Class::Bard))));
+    }
+
+    #[test]
+    fn container_moves_focus_with_unused_arrows() {
+        let mut container: Container<TestBackend> = Container::with_items(
+            String::from("spells"),
+            vec![
+                (Constraint::Length(3), Box::new(SearchBar::new("Search"))),
+                (Constraint::Min(0), Box::new(Level::new("Level", 9, Action::Level))),
+            ],
+            Direction::Vertical,
+        );
+        container.hover(true);
+        assert!(matches!(press(&mut container, Key::Down), Some(Action::Consumed)));
+        assert_eq!(container.selected, 1);
+        // The level picker takes the arrows once it is selected
+        press(&mut container, Key::Char('\n'));
+        press(&mut container, Key::Down);
+        assert_eq!(container.selected, 1);
+        assert!(matches!(press(&mut container, Key::Char('\n')), Some(Action::Level(1))));
+
+        assert!(press(&mut container, Key::Down).is_none());
+        press(&mut container, Key::Up);
+        assert_eq!(container.selected, 0);
+    }
 }
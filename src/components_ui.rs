@@ -1,69 +1,39 @@
-use crate::app::{Component, StatefulComponent};
-use crate::components::{Container, Level, SearchBar,
-                        SearchResults, SelectState, StatefulList};
-use crate::db_utils::Spell;
+use crate::app::{Component, Context, ExportPrompt, ImportJob, StatefulComponent};
+use crate::app::Action;
+use crate::components::{AboutData, Container, ItemResults, Level, ProgressionTable,
+                        SearchBar, SearchResults, SelectState, SourceList, StatefulList};
+use dnd_tui::db_utils::{GlossaryEntry, Import, Item, Spell, SRD_ATTRIBUTION};
+use dnd_tui::models::{Class, ItemCategory, Rarity, School};
+use crate::config::theme;
+use crate::filters::{Attunement, FilterValue};
+use crate::layout::{Node, Widget};
+use crate::markup;
 
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::Frame;
 use tui::backend::Backend;
-use tui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
-use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, BorderType, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph,
+                   Row, Table, Wrap};
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
-use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Default)]
-pub struct SpellSearch;
-
-impl<B: Backend> Component<B> for Container<SpellSearch, B> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, spells: &Option<Vec<Spell>>) {
-        let chunks = Layout::default()
-            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-            .split(area);
-
-        self.children[0].draw(f, chunks[0], &None);
-        self.children[1].draw(f, chunks[1], spells);
-    }
-}
-
-#[derive(Default)]
-pub struct SearchMain;
-
-impl<B: Backend> Component<B> for Container<SearchMain, B> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, spells: &Option<Vec<Spell>>) {
-        let chunks = Layout::default()
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(80)
-            ].as_ref())
-            .direction(Direction::Horizontal)
-            .split(area);
-
-        self.children[0].draw(f, chunks[0], &None);
-        self.children[1].draw(f, chunks[1], spells);
-    }
-}
-
-#[derive(Default)]
-pub struct Filters;
-
-impl<B: Backend> Component<B> for Container<Filters, B> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Option<Vec<Spell>>) {
+impl<B: Backend> Component<B> for Container<B> {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
         let chunks = Layout::default()
-            .constraints([
-                Constraint::Percentage(45),
-                Constraint::Percentage(45),
-                Constraint::Percentage(10)
-            ].as_ref())
+            .constraints(self.constraints.clone())
+            .direction(self.direction.clone())
             .split(area);
 
-        self.children[0].draw(f, chunks[0], &None);
-        self.children[1].draw(f, chunks[1], &None);
-        self.children[2].draw(f, chunks[2], &None);
+        for (child, chunk) in self.children.iter_mut().zip(chunks) {
+            child.draw(f, chunk, context);
+        }
     }
 }
 
 impl<'a, B: Backend> Component<B> for SearchBar<'a> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Option<Vec<Spell>>) {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Context) {
+        self.area = area;
         let paragraph = Paragraph::new(Spans::from(vec![
                 Span::raw(self.value.as_str())
             ]))
@@ -71,14 +41,8 @@ impl<'a, B: Backend> Component<B> for SearchBar<'a> {
                 Block::default()
                 .title(self.name)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(
-                        match self.selected {
-                            SelectState::None => Color::Gray,
-                            SelectState::Highlighted => Color::Blue,
-                            SelectState::Selected => Color::Yellow,
-                        }
-                    )
-                )
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
             )
             .alignment(Alignment::Left);
 
@@ -87,11 +51,13 @@ impl<'a, B: Backend> Component<B> for SearchBar<'a> {
 }
 
 impl<'a, B: Backend> Component<B> for Level<'a> {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Option<Vec<Spell>>) {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Context) {
+        self.area = area;
         let paragraph = Paragraph::new(Spans::from(vec![
-                Span::raw(match self.level {
-                        1..=19 => self.level.to_string(),
-                        _ => String::from("Any"),
+                Span::raw(if (1..=self.max).contains(&self.level) {
+                        self.level.to_string()
+                    } else {
+                        String::from("Any")
                     }
                 )
             ]))
@@ -99,14 +65,8 @@ impl<'a, B: Backend> Component<B> for Level<'a> {
                 Block::default()
                 .title(self.name)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(
-                        match self.selected {
-                            SelectState::None => Color::Gray,
-                            SelectState::Highlighted => Color::Blue,
-                            SelectState::Selected => Color::Yellow,
-                        }
-                    )
-                )
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
             )
             .alignment(Alignment::Left);
 
@@ -114,28 +74,20 @@ impl<'a, B: Backend> Component<B> for Level<'a> {
     }
 }
 
-impl<'a, T, B> Component<B> for StatefulList<'a, T>
-where
-    T: Into<Cow<'static, str>> + Clone, B: Backend
-{
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Option<Vec<Spell>>) {
+impl<'a, T: FilterValue, B: Backend> Component<B> for StatefulList<'a, T> {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, _: &Context) {
+        self.area.update(area, 0, self.state.selected());
         let items: Vec<ListItem> = self.items
             .iter()
-            .map(|i| ListItem::new(vec![Spans::from(Span::raw(i.clone()))]))
+            .map(|i| ListItem::new(vec![Spans::from(Span::raw(i.label()))]))
             .collect();
 
         let items = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
                 .title(self.name)
-                .border_style(Style::default().fg(
-                        match self.selected {
-                            SelectState::None => Color::Gray,
-                            SelectState::Highlighted => Color::Blue,
-                            SelectState::Selected => Color::Yellow,
-                        }
-                    )
-                )
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
             )
             .highlight_style(Style::default().add_modifier(Modifier::BOLD))
             .highlight_symbol("> ");
@@ -144,25 +96,307 @@ where
     }
 }
 
+/// Focused panes get a thick border, so focus shows even without colors.
+fn border_type(state: &SelectState) -> BorderType {
+    match state {
+        SelectState::None => BorderType::Plain,
+        SelectState::Highlighted | SelectState::Selected => BorderType::Thick,
+    }
+}
+
+/// A rectangle of the given percentage size centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ].as_ref())
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ].as_ref())
+        .split(vertical[1])[1]
+}
+
+/// Drawn over the current view while an import runs.
+pub fn import_popup<B: Backend>(f: &mut Frame<B>, area: Rect, job: &ImportJob) {
+    let area = centered_rect(60, 20, area);
+    f.render_widget(Clear, area);
+
+    if let Some(error) = &job.failed {
+        let paragraph = Paragraph::new(error.as_str())
+            .block(
+                Block::default()
+                .title("Import failed, Esc to close")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme().error))
+            )
+            .wrap(Wrap{ trim: false });
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let (label, ratio) = match &job.progress {
+        Some(progress) => (
+            format!("{} {}/{}: {}", progress.kind, progress.done + 1, progress.total, progress.name),
+            progress.done as f64 / progress.total.max(1) as f64,
+        ),
+        None => (String::from("Reading data"), 0.0),
+    };
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+            .title("Importing, Esc to cancel")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme().selected))
+        )
+        .gauge_style(Style::default().fg(theme().highlighted))
+        .ratio(ratio)
+        .label(label);
+    f.render_widget(gauge, area);
+}
+
+pub fn export_popup<B: Backend>(f: &mut Frame<B>, area: Rect, prompt: &ExportPrompt) {
+    let text = vec![
+        Spans::from(format!(
+            "Export {} spell(s) as {}",
+            prompt.spells.len(),
+            prompt.format.name()
+        )),
+        Spans::from(Span::styled(
+            "Tab changes format, Enter writes, Esc cancels",
+            Style::default().fg(theme().muted)
+        )),
+        Spans::default(),
+        Spans::from(vec![
+            Span::styled("File: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(prompt.path.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]),
+    ];
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+            .title("Export")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme().selected))
+        )
+        .wrap(Wrap{ trim: false });
+    let area = centered_rect(60, 30, area);
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// The lines of a spell card, shared by the TUI and the `show` command.
+pub fn spell_card(spell: &Spell, glossary: &[GlossaryEntry]) -> Vec<Spans<'static>> {
+    let mut text = vec![
+        Spans::from(vec![
+            Span::styled(
+                spell.name.clone(),
+                Style::default().add_modifier(Modifier::BOLD)
+            ),
+            Span::from("\n"),
+        ]),
+        Spans::from(vec![
+            Span::styled(
+                format!("{}", spell.level),
+                Style::default().add_modifier(Modifier::ITALIC)
+            ),
+            Span::styled(
+                match spell.level {
+                    1 => "st",
+                    2 => "nd",
+                    3 => "rd",
+                    _ => "th",
+                },
+                Style::default().add_modifier(Modifier::ITALIC)
+            ),
+            Span::styled(
+                " level ",
+                Style::default().add_modifier(Modifier::ITALIC)
+            ),
+            Span::styled(
+                spell.school.clone(),
+                Style::default().add_modifier(Modifier::ITALIC)
+            ),
+            Span::from("\n"),
+        ]),
+        Spans::from(vec![
+            Span::styled(
+                "Casting time: ",
+                Style::default().add_modifier(Modifier::BOLD)
+            ),
+            Span::from(spell.casting_time.clone()),
+        ]),
+        if let Some(range) = spell.range.clone() {
+            Spans::from(vec![
+                Span::styled(
+                    "Range: ",
+                    Style::default().add_modifier(Modifier::BOLD)
+                ),
+                Span::from(range),
+            ])
+        } else { Spans::from(vec![]) },
+        Spans::from(vec![
+            Span::styled(
+                "Materials: ",
+                Style::default().add_modifier(Modifier::BOLD)
+            ),
+            if spell.verbal {
+                Span::from("V ")
+            } else { Span::from("") },
+            if spell.somatic {
+                Span::from("S ")
+            } else { Span::from("") },
+            if spell.material {
+                Span::from("M ")
+            } else { Span::from("") },
+            if let Some(material) = spell.material_text.clone() {
+                Span::from("(".to_owned() + &material + ")")
+            } else { Span::from("") },
+        ]),
+        Spans::from(vec![
+            Span::styled(
+                "Source: ",
+                Style::default().add_modifier(Modifier::BOLD)
+            ),
+            Span::from(spell.source.clone()),
+        ]),
+    ];
+
+    text.push(Spans::default());
+    text.extend(markup::render(&spell.description, glossary));
+    if let Some(higher_level) = &spell.higher_level {
+        let mut higher_level = markup::render(higher_level, glossary);
+        if let Some(first) = higher_level.first_mut() {
+            first.0.insert(0, Span::styled(
+                "At higher levels: ",
+                Style::default().add_modifier(Modifier::BOLD)
+            ));
+        }
+        text.push(Spans::default());
+        text.extend(higher_level);
+    }
+    text
+}
+
 impl SearchResults {
-    fn list<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+    fn list<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, favorites: &HashSet<String>) {
         let items: Vec<ListItem> = self.items
             .iter()
-            .map(|i| ListItem::new(vec![Spans::from(Span::raw(i.name.clone()))]))
+            .map(|spell| {
+                let star = if favorites.contains(&spell.name) { "★ " } else { "" };
+                ListItem::new(vec![Spans::from(Span::raw(format!("{}{}", star, spell.name)))])
+            })
             .collect();
 
         let items = List::new(items)
             .block(Block::default()
                 .borders(Borders::ALL)
                 .title("Search Results")
-                .border_style(Style::default().fg(
-                        match self.selected {
-                            SelectState::None => Color::Gray,
-                            SelectState::Highlighted => Color::Blue,
-                            SelectState::Selected => Color::Yellow,
-                        }
-                    )
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(items, area, &mut self.state);
+    }
+
+    fn card<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        i: usize,
+        glossary: &[GlossaryEntry]
+    ) {
+        let text = spell_card(&self.items[i], glossary);
+
+        let paragraph = Paragraph::new(text)
+            .block(
+                Block::default()
+                .title("Search Results")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
+            )
+            .wrap(Wrap{ trim: false })
+            .scroll((self.scroll, 0))
+            .alignment(Alignment::Left);
+        f.render_widget(paragraph, area);
+    }
+
+    fn glossary_popup<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        i: usize,
+        popup: usize,
+        glossary: &[GlossaryEntry]
+    ) {
+        let terms = self.card_terms(i, glossary);
+        if let Some(entry) = terms.get(popup) {
+            let paragraph = Paragraph::new(markup::render(&entry.definition, &[]))
+                .block(
+                    Block::default()
+                    .title(format!("{} ({}/{})", entry.term, popup + 1, terms.len()))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme().link))
                 )
+                .wrap(Wrap{ trim: false })
+                .alignment(Alignment::Left);
+            let area = centered_rect(70, 60, area);
+            f.render_widget(Clear, area);
+            f.render_widget(paragraph, area);
+        }
+    }
+}
+
+impl<B: Backend> Component<B> for SearchResults {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
+        if let Some(spells) = &context.search_results {
+            self.items = spells.clone();
+            // Typing in the search bar can shrink the results under the
+            // highlighted row
+            if self.state.selected().is_some_and(|i| i >= self.items.len()) {
+                self.state.select(None);
+                self.spell_card = false;
+                self.popup = None;
+            }
+        }
+        self.area.update(area, 0, self.state.selected());
+
+        if !self.spell_card {
+            self.list(f, area, &context.favorites);
+        } else if let Some(i) = self.state.selected() {
+            self.card(f, area, i, &context.glossary);
+            if let Some(popup) = self.popup {
+                self.glossary_popup(f, area, i, popup, &context.glossary);
+            }
+        }
+    }
+}
+
+impl ItemResults {
+    fn list<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let items: Vec<ListItem> = self.items
+            .iter()
+            .map(|i| ListItem::new(vec![Spans::from(Span::raw(i.name().to_owned()))]))
+            .collect();
+
+        let items = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Items")
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
             )
             .highlight_style(Style::default().add_modifier(Modifier::BOLD))
             .highlight_symbol("> ");
@@ -171,185 +405,352 @@ impl SearchResults {
     }
 
     fn card<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, i: usize) {
-        let paragraph = Paragraph::new(vec![
-            Spans::from(vec![
-                Span::styled(
-                    self.items[i].name.clone(),
-                    Style::default().add_modifier(Modifier::BOLD)
-                ),
-                Span::from("\n"),
-            ]),
-            Spans::from(vec![
-                Span::styled(
-                    format!("{}", self.items[i].level),
-                    Style::default().add_modifier(Modifier::ITALIC)
-                ),
-                Span::styled(
-                    match self.items[i].level {
-                        1 => "st",
-                        2 => "nd",
-                        3 => "rd",
-                        _ => "th",
-                    },
-                    Style::default().add_modifier(Modifier::ITALIC)
-                ),
-                Span::styled(
-                    " level ",
-                    Style::default().add_modifier(Modifier::ITALIC)
-                ),
-                Span::styled(
-                    self.items[i].school.clone(),
-                    Style::default().add_modifier(Modifier::ITALIC)
-                ),
-                Span::from("\n"),
-            ]),
+        let mut text = vec![
             Spans::from(vec![
                 Span::styled(
-                    "Casting time: ",
+                    self.items[i].name().to_owned(),
                     Style::default().add_modifier(Modifier::BOLD)
                 ),
-                Span::from(self.items[i].casting_time.clone()),
             ]),
-            if let Some(range) = self.items[i].range.clone() {
-                Spans::from(vec![
+        ];
+
+        match &self.items[i] {
+            Item::Equipment(item) => {
+                text.push(Spans::from(vec![
                     Span::styled(
-                        "Range: ",
-                        Style::default().add_modifier(Modifier::BOLD)
+                        match &item.subcategory {
+                            Some(subcategory) => format!("{} ({})", item.category, subcategory),
+                            None => item.category.clone(),
+                        },
+                        Style::default().add_modifier(Modifier::ITALIC)
                     ),
-                    Span::from(range),
-                ])
-            } else { Spans::from(vec![]) },
-            Spans::from(vec![
-                Span::styled(
-                    "Materials: ",
-                    Style::default().add_modifier(Modifier::BOLD)
-                ),
-                if self.items[i].verbal {
-                    Span::from("V ")
-                } else { Span::from("") },
-                if self.items[i].somatic {
-                    Span::from("S ")
-                } else { Span::from("") },
-                if self.items[i].material {
-                    Span::from("M ")
-                } else { Span::from("") },
-                if let Some(material) = self.items[i].material_text.clone() {
-                    Span::from("(".to_owned() + &material + ")")
-                } else { Span::from("") },
-            ]),
-            Spans::from(vec![
-                Span::from("\n"),
-                Span::from(self.items[i].description.clone()),
-                Span::from("\n"),
-            ]),
-            if let Some(higher_level) = self.items[i].higher_level.clone() {
-                Spans::from(vec![
+                ]));
+                let fields = [
+                    ("Cost: ", item.cost.clone()),
+                    ("Weight: ", item.weight.map(|weight| format!("{} lb.", weight))),
+                    ("Damage: ", item.damage.clone()),
+                    ("Armor class: ", item.armor_class.clone()),
+                    ("Properties: ", Some(item.properties.clone()).filter(|p| !p.is_empty())),
+                ];
+                for (label, value) in fields.iter() {
+                    if let Some(value) = value {
+                        text.push(Spans::from(vec![
+                            Span::styled(*label, Style::default().add_modifier(Modifier::BOLD)),
+                            Span::from(value.clone()),
+                        ]));
+                    }
+                }
+                text.push(Spans::from(vec![]));
+                text.extend(markup::render(&item.description, &[]));
+            },
+            Item::Magic(item) => {
+                text.push(Spans::from(vec![
                     Span::styled(
-                        "At higher levels: ",
-                        Style::default().add_modifier(Modifier::BOLD)
+                        format!(
+                            "{}, {}{}",
+                            item.category,
+                            item.rarity,
+                            if item.attunement { " (requires attunement)" } else { "" }
+                        ),
+                        Style::default().add_modifier(Modifier::ITALIC)
                     ),
-                    Span::from(higher_level),
-                ])
-            } else { Spans::from(vec![]) },
-        ])
+                ]));
+                text.push(Spans::from(vec![]));
+                text.extend(markup::render(&item.description, &[]));
+            },
+        }
+
+        let paragraph = Paragraph::new(text)
             .block(
                 Block::default()
-                .title("Search Results")
+                .title("Items")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(
-                        match self.selected {
-                            SelectState::None => Color::Gray,
-                            SelectState::Highlighted => Color::Blue,
-                            SelectState::Selected => Color::Yellow,
-                        }
-                    )
-                )
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
             )
             .wrap(Wrap{ trim: false })
+            .scroll((self.scroll, 0))
             .alignment(Alignment::Left);
         f.render_widget(paragraph, area);
     }
 }
 
-impl<B: Backend> Component<B> for SearchResults {
-    fn draw(&mut self, f: &mut Frame<B>, area: Rect, spells: &Option<Vec<Spell>>) {
-        if let Some(spells) = spells {
-            self.items = spells.clone();
+impl<B: Backend> Component<B> for ItemResults {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
+        if let Some(items) = &context.item_results {
+            self.items = items.clone();
+            // A filter change can shrink the results under the highlighted
+            // row, as with spells
+            if self.state.selected().is_some_and(|i| i >= self.items.len()) {
+                self.state.select(None);
+                self.item_card = false;
+            }
         }
+        self.area.update(area, 0, self.state.selected());
 
-        if !self.spell_card {
+        if !self.item_card {
             self.list(f, area);
-        } else {
-            if let Some(i) = self.state.selected() {
-                self.card(f, area, i);
-            }
+        } else if let Some(i) = self.state.selected() {
+            self.card(f, area, i);
+        }
+    }
+}
+
+fn ordinal(level: usize) -> String {
+    format!("{}{}", level, match level {
+        1 => "st",
+        2 => "nd",
+        3 => "rd",
+        _ => "th",
+    })
+}
+
+impl<B: Backend> Component<B> for ProgressionTable {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
+        let class_levels = context.class_levels.as_deref().unwrap_or(&[]);
+        self.rows = class_levels.len();
+        self.area.update(area, 1, self.state.selected());
+
+        let header = Row::new(
+            vec![String::from("Level"), String::from("Cantrips"), String::from("Spells")]
+                .into_iter()
+                .chain((1..=9).map(ordinal))
+                .map(|title| Cell::from(title).style(Style::default().add_modifier(Modifier::BOLD)))
+        );
+        let rows = class_levels.iter().map(|class_level| {
+            let known = |known: Option<i32>| known.map_or(String::from("-"), |known| known.to_string());
+            Row::new(
+                vec![
+                    class_level.level.to_string(),
+                    known(class_level.cantrips_known),
+                    known(class_level.spells_known),
+                ].into_iter()
+                .chain(class_level.slots().iter().map(|slots| match slots {
+                    0 => String::from("-"),
+                    slots => slots.to_string(),
+                }))
+                .map(Cell::from)
+            )
+        });
+
+        let widths = [Constraint::Length(6), Constraint::Length(9), Constraint::Length(7)]
+            .iter()
+            .cloned()
+            .chain((1..=9).map(|_| Constraint::Length(4)))
+            .collect::<Vec<Constraint>>();
+
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(match class_levels.first() {
+                    Some(class_level) => format!("Spellcasting: {}", class_level.class),
+                    None => String::from("Spellcasting"),
+                })
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
+            )
+            .widths(&widths)
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+impl<B: Backend> Component<B> for SourceList {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
+        if let Some(sources) = &context.sources {
+            self.items = sources.clone();
+        }
+        self.area.update(area, 0, self.state.selected());
+
+        let items: Vec<ListItem> = self.items
+            .iter()
+            .map(|(source, count)| ListItem::new(vec![Spans::from(vec![
+                Span::raw(if source.active { "[x] " } else { "[ ] " }),
+                Span::styled(source.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" ({} spells)", count)),
+            ])]))
+            .collect();
+
+        let items = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(match context.campaigns.iter().find(|campaign| campaign.current) {
+                    Some(campaign) if context.campaigns.len() > 1 => {
+                        format!("Sources: {} campaign (←/→ to switch)", campaign.name)
+                    },
+                    Some(campaign) => format!("Sources: {} campaign", campaign.name),
+                    None => String::from("Sources"),
+                })
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(items, area, &mut self.state);
+    }
+}
+
+impl<B: Backend> Component<B> for AboutData {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, context: &Context) {
+        self.area = area;
+        let imports = context.imports.as_deref().unwrap_or(&[]);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut text = vec![Spans::from(Span::styled("Imported data", bold))];
+
+        match imports.first() {
+            Some(latest) => {
+                let run: Vec<&Import> = imports.iter()
+                    .take_while(|import| import.imported_at == latest.imported_at)
+                    .collect();
+                text.push(Spans::from(format!(
+                    "Imported {} UTC by dnd_tui {}",
+                    latest.imported_at.format("%Y-%m-%d %H:%M:%S"),
+                    latest.importer_version
+                )));
+                text.push(Spans::default());
+                for import in run.iter() {
+                    text.push(Spans::from(vec![
+                        Span::styled(format!("{:<14}", import.kind), bold),
+                        Span::raw(format!("{:>5} records  ", import.records)),
+                        Span::styled(
+                            import.content_hash.chars().take(12).collect::<String>(),
+                            Style::default().fg(theme().muted)
+                        ),
+                        Span::raw(format!("  {}", import.source_path)),
+                    ]));
+                }
+                let earlier = imports.iter()
+                    .filter(|import| import.imported_at != latest.imported_at)
+                    .map(|import| import.imported_at)
+                    .collect::<std::collections::BTreeSet<_>>()
+                    .len();
+                if earlier > 0 {
+                    text.push(Spans::default());
+                    text.push(Spans::from(format!("{} earlier import(s) recorded", earlier)));
+                }
+            },
+            None => text.push(Spans::from("No import has been recorded for this database")),
         }
+
+        text.push(Spans::default());
+        text.push(Spans::from(Span::styled("Attribution", bold)));
+        text.push(Spans::from(SRD_ATTRIBUTION));
+
+        let paragraph = Paragraph::new(text)
+            .block(
+                Block::default()
+                .title("About data")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme().border_color(&self.selected)))
+                .border_type(border_type(&self.selected))
+            )
+            .wrap(Wrap{ trim: false })
+            .scroll((self.scroll, 0))
+            .alignment(Alignment::Left);
+        f.render_widget(paragraph, area);
     }
 }
 
-impl<B: Backend> StatefulComponent<B> for Container<SpellSearch, B> {}
-impl<B: Backend> StatefulComponent<B> for Container<SearchMain, B> {}
-impl<B: Backend> StatefulComponent<B> for Container<Filters, B> {}
+impl<B: Backend> StatefulComponent<B> for Container<B> {}
 impl<'a, B: Backend> StatefulComponent<B> for SearchBar<'a> {}
 impl<'a, B: Backend> StatefulComponent<B> for Level<'a> {}
 impl<B: Backend> StatefulComponent<B> for SearchResults {}
-impl<'a, T, B: Backend> StatefulComponent<B> for StatefulList<'a, T>
-where
-    T: Into<Cow<'static, str>> + Clone + Into<String> {}
-
-pub fn build_component_tree<B: 'static + Backend>() 
-    -> Container<SpellSearch, B> {
-    let root: Container<SpellSearch, B> = Container::with_items(
-        vec![
-            Box::new(SearchBar::new("Search")),
-            Box::new(Container::<SearchMain, B>::with_items(
-                vec![
-                    Box::new(Container::<Filters, B>::with_items(
-                        vec![
-                            Box::new(StatefulList::with_items(
-                                vec![
-                                    "Barbarian",
-                                    "Bard",
-                                    "Cleric",
-                                    "Druid",
-                                    "Fighter",
-                                    "Monk",
-                                    "Paladin",
-                                    "Ranger",
-                                    "Rogue",
-                                    "Sorcerer",
-                                    "Warlock",
-                                    "Wizard"
-                                ],
-                                "Class"
-                            )),
-                            Box::new(StatefulList::with_items(
-                                vec![
-                                    "Abjuration",
-                                    "Conjuration",
-                                    "Divination",
-                                    "Enchantment",
-                                    "Evocation",
-                                    "Illusion",
-                                    "Necromancy",
-                                    "Transmutation",
-                                ],
-                                "School"
-                            )),
-                            Box::new(
-                                Level::new("Level")
-                            )
-                        ],
-                        Direction::Vertical
-                    )),
-                    Box::new(SearchResults::with_items(
-                        vec![],
-                    )),
-                ],
-                Direction::Horizontal
-            ))
-        ],
-        Direction::Vertical
+impl<B: Backend> StatefulComponent<B> for ItemResults {}
+impl<B: Backend> StatefulComponent<B> for ProgressionTable {}
+impl<B: Backend> StatefulComponent<B> for SourceList {}
+impl<B: Backend> StatefulComponent<B> for AboutData {}
+impl<'a, T: FilterValue, B: Backend> StatefulComponent<B> for StatefulList<'a, T> {}
+
+/// Builds the container `node` describes, named `name`, with `widget`
+/// making the widgets in it and any `sizes` saved for its panes applied.
+fn build_container<B: 'static + Backend>(
+    node: &Node,
+    name: &str,
+    widget: fn(Widget) -> Box<dyn StatefulComponent<B>>,
+    sizes: &HashMap<String, u16>
+) -> Container<B> {
+    let children = node.panes
+        .iter()
+        .enumerate()
+        .map(|(i, pane)| {
+            let child: Box<dyn StatefulComponent<B>> = match pane.widget {
+                Some(kind) => widget(kind),
+                None => Box::new(
+                    build_container(pane, &format!("{}.panes[{}]", name, i), widget, sizes)
+                ),
+            };
+            (pane.size.0, child)
+        })
+        .collect();
+    let mut container = Container::with_items(
+        name.to_owned(),
+        children,
+        node.direction.expect("Containers are checked when the layout is loaded").into()
     );
-    root
+    container.restore_sizes(sizes);
+    container
+}
+
+/// The filter widgets, which only report what was picked and so fit in any
+/// view.
+fn filter_widget<B: 'static + Backend>(widget: Widget) -> Box<dyn StatefulComponent<B>> {
+    match widget {
+        Widget::Search => Box::new(SearchBar::new("Search")),
+        Widget::Class => Box::new(StatefulList::with_items(Class::ALL.to_vec(), "Class")),
+        Widget::School => Box::new(StatefulList::with_items(School::ALL.to_vec(), "School")),
+        Widget::Level => Box::new(Level::new("Level", 9, Action::Level)),
+        Widget::CharacterLevel => {
+            Box::new(Level::new("Character level", 20, Action::CharacterLevel))
+        },
+        Widget::Category => {
+            Box::new(StatefulList::with_items(ItemCategory::ALL.to_vec(), "Category"))
+        },
+        Widget::Rarity => Box::new(StatefulList::with_items(Rarity::ALL.to_vec(), "Rarity")),
+        Widget::Attunement => {
+            Box::new(StatefulList::with_items(Attunement::ALL.to_vec(), "Attunement"))
+        },
+        Widget::SpellResults | Widget::ItemResults | Widget::Progression => {
+            panic!("Results are checked to be in their own view when the layout is loaded")
+        },
+    }
+}
+
+fn spell_widget<B: 'static + Backend>(widget: Widget) -> Box<dyn StatefulComponent<B>> {
+    match widget {
+        Widget::SpellResults => Box::new(SearchResults::with_items(vec![])),
+        widget => filter_widget(widget),
+    }
+}
+
+fn item_widget<B: 'static + Backend>(widget: Widget) -> Box<dyn StatefulComponent<B>> {
+    match widget {
+        Widget::ItemResults => Box::new(ItemResults::with_items(vec![])),
+        widget => filter_widget(widget),
+    }
+}
+
+fn class_widget<B: 'static + Backend>(widget: Widget) -> Box<dyn StatefulComponent<B>> {
+    match widget {
+        Widget::Progression => Box::new(ProgressionTable::new()),
+        widget => filter_widget(widget),
+    }
+}
+
+pub fn build_component_tree<B: 'static + Backend>(layout: &Node, sizes: &HashMap<String, u16>)
+    -> Container<B> {
+    build_container(layout, "spells", spell_widget, sizes)
+}
+
+pub fn build_item_tree<B: 'static + Backend>(layout: &Node, sizes: &HashMap<String, u16>)
+    -> Container<B> {
+    build_container(layout, "items", item_widget, sizes)
+}
+
+pub fn build_class_tree<B: 'static + Backend>(layout: &Node, sizes: &HashMap<String, u16>)
+    -> Container<B> {
+    build_container(layout, "classes", class_widget, sizes)
 }
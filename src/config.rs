@@ -0,0 +1,594 @@
+//! Settings, read from `config.toml` in the XDG config directory
+//! (`~/.config/dnd-tui/config.toml` by default). Most settings can also be
+//! given by an environment variable and some by a command line flag. A flag
+//! wins over the environment, which wins over the file, which wins over the
+//! default.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use log::LevelFilter;
+use serde::Deserialize;
+use tui::style::Color;
+
+use dnd_tui::db_utils::ImportPaths;
+use crate::cli::Cli;
+use crate::layout::Layouts;
+use crate::components::{Pane, SelectState};
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    database: Option<String>,
+    tick_rate: Option<u64>,
+    layout: Option<String>,
+    imports: FileImports,
+    export: FileExport,
+    log: FileLog,
+    theme: FileTheme,
+    keys: FileKeys,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileImports {
+    spells: Option<String>,
+    sources_dir: Option<String>,
+    equipment: Option<String>,
+    magic_items: Option<String>,
+    conditions: Option<String>,
+    rules: Option<String>,
+    levels: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileExport {
+    templates_dir: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileLog {
+    file: Option<String>,
+    level: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileTheme {
+    border: Option<String>,
+    highlighted: Option<String>,
+    selected: Option<String>,
+    link: Option<String>,
+    error: Option<String>,
+    muted: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileKeys {
+    quit: Option<String>,
+    spells: Option<String>,
+    items: Option<String>,
+    classes: Option<String>,
+    sources: Option<String>,
+    about: Option<String>,
+    clear: Option<String>,
+    import: Option<String>,
+    export: Option<String>,
+    copy: Option<String>,
+    copy_name: Option<String>,
+    favorite: Option<String>,
+    search: Option<String>,
+    class: Option<String>,
+    school: Option<String>,
+    level: Option<String>,
+    results: Option<String>,
+    shrink: Option<String>,
+    grow: Option<String>,
+}
+
+/// The colors everything is drawn with.
+#[derive(Clone)]
+pub struct Theme {
+    /// Borders of panes that aren't focused
+    pub border: Color,
+    /// The focused pane's border and the import progress bar
+    pub highlighted: Color,
+    /// Borders of selected panes and popups, and the current tab
+    pub selected: Color,
+    /// Glossary terms in spell descriptions
+    pub link: Color,
+    pub error: Color,
+    /// Hints and other secondary text
+    pub muted: Color,
+}
+
+impl Theme {
+    pub fn border_color(&self, state: &SelectState) -> Color {
+        match state {
+            SelectState::None => self.border,
+            SelectState::Highlighted => self.highlighted,
+            SelectState::Selected => self.selected,
+        }
+    }
+}
+
+/// What a key does, see `Keys::binding`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Binding {
+    Quit,
+    Spells,
+    Items,
+    Classes,
+    Sources,
+    About,
+    Clear,
+    Import,
+    Export,
+    Copy,
+    CopyName,
+    /// Marks the spell picked in the results as a favorite, or unmarks it
+    Favorite,
+    /// Jumps straight to a pane of the current view
+    Focus(Pane),
+    /// Narrows the focused pane's column
+    Shrink,
+    /// Widens the focused pane's column
+    Grow,
+}
+
+/// The keys bound to each `Binding`. Arrow keys, Tab, Enter and Esc can't
+/// be rebound.
+#[derive(Clone)]
+pub struct Keys {
+    pub quit: char,
+    pub spells: char,
+    pub items: char,
+    pub classes: char,
+    pub sources: char,
+    pub about: char,
+    pub clear: char,
+    pub import: char,
+    pub export: char,
+    pub copy: char,
+    pub copy_name: char,
+    pub favorite: char,
+    pub search: char,
+    pub class: char,
+    pub school: char,
+    pub level: char,
+    pub results: char,
+    pub shrink: char,
+    pub grow: char,
+}
+
+impl Keys {
+    fn bindings(&self) -> [(Binding, &'static str, char); 19] {
+        [
+            (Binding::Quit, "quit", self.quit),
+            (Binding::Spells, "spells", self.spells),
+            (Binding::Items, "items", self.items),
+            (Binding::Classes, "classes", self.classes),
+            (Binding::Sources, "sources", self.sources),
+            (Binding::About, "about", self.about),
+            (Binding::Clear, "clear", self.clear),
+            (Binding::Import, "import", self.import),
+            (Binding::Export, "export", self.export),
+            (Binding::Copy, "copy", self.copy),
+            (Binding::CopyName, "copy_name", self.copy_name),
+            (Binding::Favorite, "favorite", self.favorite),
+            (Binding::Focus(Pane::Search), "search", self.search),
+            (Binding::Focus(Pane::Class), "class", self.class),
+            (Binding::Focus(Pane::School), "school", self.school),
+            (Binding::Focus(Pane::Level), "level", self.level),
+            (Binding::Focus(Pane::Results), "results", self.results),
+            (Binding::Shrink, "shrink", self.shrink),
+            (Binding::Grow, "grow", self.grow),
+        ]
+    }
+
+    pub fn binding(&self, key: char) -> Option<Binding> {
+        self.bindings()
+            .iter()
+            .find(|(_, _, bound)| *bound == key)
+            .map(|(binding, _, _)| *binding)
+    }
+}
+
+/// A setting as resolved, for `config check`.
+#[derive(Clone)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: Option<String>,
+    /// The flag, environment variable, file or default the value came from
+    pub origin: String,
+}
+
+#[derive(Clone)]
+pub struct Config {
+    /// The config file that was read, if there was one
+    pub file: Option<PathBuf>,
+    pub database: String,
+    pub imports: ImportPaths,
+    /// Export templates found here replace the built-in ones
+    pub templates_dir: Option<PathBuf>,
+    pub log_file: PathBuf,
+    pub log_level: LevelFilter,
+    pub tick_rate: Duration,
+    /// The built-in layouts, with any views the layout file sets replaced
+    pub layouts: Layouts,
+    pub theme: Theme,
+    pub keys: Keys,
+    pub settings: Vec<Setting>,
+}
+
+/// `~/.config/dnd-tui/config.toml`, or the same under `$XDG_CONFIG_HOME`.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("dnd-tui").join("config.toml"))
+}
+
+/// Expands a leading `~/` to the home directory.
+fn path(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(value),
+    }
+}
+
+fn color(value: &str) -> Result<Color, String> {
+    let color = match value.to_lowercase().replace(['-', ' '], "_").as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "dark_gray" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "white" => Color::White,
+        // Slicing by byte needs every character to be one byte long
+        hex if hex.starts_with('#') && hex.len() == 7 && hex.is_ascii() => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            match (channel(1), channel(3), channel(5)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("`{}` isn't a #rrggbb color", value)),
+            }
+        },
+        index => match index.parse() {
+            Ok(index) => Color::Indexed(index),
+            Err(_) => return Err(format!(
+                "`{}` isn't a color name, #rrggbb or a number from 0 to 255",
+                value
+            )),
+        },
+    };
+    Ok(color)
+}
+
+fn key(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_control() => Ok(c),
+        _ => Err(format!("`{}` should be a single character", value)),
+    }
+}
+
+/// Resolves settings one at a time, remembering where each came from and
+/// every value that couldn't be used.
+struct Loader {
+    settings: Vec<Setting>,
+    errors: Vec<String>,
+}
+
+impl Loader {
+    /// The value from `flag`, then the environment variable `var`, then the
+    /// file, falling back to `default`.
+    fn value(
+        &mut self,
+        name: &'static str,
+        flag: Option<(&str, &String)>,
+        var: Option<&str>,
+        file: Option<String>,
+        default: Option<&str>,
+    ) -> Option<String> {
+        let (value, origin) = if let Some((flag, value)) = flag {
+            (Some(value.clone()), format!("--{}", flag))
+        } else if let Some((var, value)) = var.and_then(|var| env::var(var).ok().map(|value| (var, value))) {
+            (Some(value), format!("${}", var))
+        } else if let Some(value) = file {
+            (Some(value), String::from("config file"))
+        } else {
+            (default.map(str::to_owned), String::from("default"))
+        };
+        self.settings.push(Setting {
+            name,
+            value: value.clone(),
+            origin,
+        });
+        value
+    }
+
+    fn parse<T>(&mut self, name: &str, value: Option<String>, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+        match value.as_deref().map(parse)? {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push(format!("{}: {}", name, error));
+                None
+            },
+        }
+    }
+
+    fn color(&mut self, name: &'static str, file: Option<String>, default: &str) -> Color {
+        let value = self.value(name, None, None, file, Some(default));
+        self.parse(name, value, color).unwrap_or(Color::Reset)
+    }
+
+    fn key(&mut self, name: &'static str, file: Option<String>, default: char) -> char {
+        let value = self.value(name, None, None, file, Some(&default.to_string()));
+        self.parse(name, value, key).unwrap_or(default)
+    }
+}
+
+impl Config {
+    /// Reads the config file and applies the environment and `cli`'s flags
+    /// over it. Every problem found is returned, not only the first.
+    pub fn load(cli: &Cli) -> Result<Config, Vec<String>> {
+        let explicit = cli.config.clone()
+            .or_else(|| env::var_os("DND_TUI_CONFIG").map(PathBuf::from));
+        let file_path = explicit.clone().or_else(default_path);
+
+        let (file, file_path) = match &file_path {
+            Some(file_path) => match fs::read_to_string(file_path) {
+                Ok(text) => match toml::from_str::<File>(&text) {
+                    Ok(file) => (file, Some(file_path.clone())),
+                    Err(error) => return Err(vec![
+                        format!("{}: {}", file_path.display(), error.to_string().trim_end())
+                    ]),
+                },
+                // Without a config file everything has its default, unless
+                // a file was asked for
+                Err(_) if explicit.is_none() => (File::default(), None),
+                Err(error) => return Err(vec![
+                    format!("Couldn't read {}: {}", file_path.display(), error)
+                ]),
+            },
+            None => (File::default(), None),
+        };
+
+        let mut loader = Loader {
+            settings: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let database = loader.value(
+            "database",
+            cli.database.as_ref().map(|value| ("database", value)),
+            Some("DATABASE_URL"),
+            file.database,
+            Some("5e.db"),
+        ).unwrap_or_default();
+
+        let mut import = |name, var, file| loader.value(name, None, Some(var), file, None).map(|value| path(&value));
+        let imports = ImportPaths {
+            spells: import("imports.spells", "SPELLS_JSON", file.imports.spells),
+            sources_dir: import("imports.sources_dir", "SOURCES_DIR", file.imports.sources_dir),
+            equipment: import("imports.equipment", "EQUIPMENT_JSON", file.imports.equipment),
+            magic_items: import("imports.magic_items", "MAGIC_ITEMS_JSON", file.imports.magic_items),
+            conditions: import("imports.conditions", "CONDITIONS_JSON", file.imports.conditions),
+            rules: import("imports.rules", "RULES_JSON", file.imports.rules),
+            levels: import("imports.levels", "LEVELS_JSON", file.imports.levels),
+        };
+        let templates_dir = loader.value(
+            "export.templates_dir",
+            None,
+            Some("TEMPLATES_DIR"),
+            file.export.templates_dir,
+            None,
+        ).map(|value| path(&value));
+
+        let log_file = loader.value(
+            "log.file",
+            cli.log_file.as_ref().map(|value| ("log-file", value)),
+            Some("DND_TUI_LOG_FILE"),
+            file.log.file,
+            Some("dnd-tui.log"),
+        ).map(|value| path(&value)).unwrap_or_default();
+        let log_level = loader.value(
+            "log.level",
+            cli.log_level.as_ref().map(|value| ("log-level", value)),
+            Some("DND_TUI_LOG_LEVEL"),
+            file.log.level,
+            Some("info"),
+        );
+        let log_level = loader.parse("log.level", log_level, |value| {
+            LevelFilter::from_str(value)
+                .map_err(|_| format!("`{}` isn't one of off, error, warn, info, debug or trace", value))
+        }).unwrap_or(LevelFilter::Info);
+
+        let tick_rate = loader.value(
+            "tick_rate",
+            None,
+            Some("DND_TUI_TICK_RATE"),
+            file.tick_rate.map(|rate| rate.to_string()),
+            Some("200"),
+        );
+        let tick_rate = loader.parse("tick_rate", tick_rate, |value| match value.parse() {
+            Ok(0) | Err(_) => Err(format!("`{}` should be a number of milliseconds above 0", value)),
+            Ok(rate) => Ok(Duration::from_millis(rate)),
+        }).unwrap_or(Duration::from_millis(200));
+
+        let layout = loader.value(
+            "layout",
+            None,
+            Some("DND_TUI_LAYOUT"),
+            file.layout,
+            None,
+        ).map(|value| path(&value));
+        let layouts = match layout {
+            Some(layout) => Layouts::load(&layout).unwrap_or_else(|errors| {
+                loader.errors.extend(errors);
+                Layouts::default()
+            }),
+            None => Layouts::default(),
+        };
+
+        let theme = Theme {
+            border: loader.color("theme.border", file.theme.border, "gray"),
+            highlighted: loader.color("theme.highlighted", file.theme.highlighted, "blue"),
+            selected: loader.color("theme.selected", file.theme.selected, "yellow"),
+            link: loader.color("theme.link", file.theme.link, "cyan"),
+            error: loader.color("theme.error", file.theme.error, "red"),
+            muted: loader.color("theme.muted", file.theme.muted, "dark_gray"),
+        };
+
+        let keys = Keys {
+            quit: loader.key("keys.quit", file.keys.quit, 'q'),
+            spells: loader.key("keys.spells", file.keys.spells, '1'),
+            items: loader.key("keys.items", file.keys.items, '2'),
+            classes: loader.key("keys.classes", file.keys.classes, '3'),
+            sources: loader.key("keys.sources", file.keys.sources, '4'),
+            about: loader.key("keys.about", file.keys.about, '5'),
+            clear: loader.key("keys.clear", file.keys.clear, 'x'),
+            import: loader.key("keys.import", file.keys.import, 'i'),
+            export: loader.key("keys.export", file.keys.export, 'e'),
+            copy: loader.key("keys.copy", file.keys.copy, 'y'),
+            copy_name: loader.key("keys.copy_name", file.keys.copy_name, 'Y'),
+            favorite: loader.key("keys.favorite", file.keys.favorite, 'f'),
+            search: loader.key("keys.search", file.keys.search, '/'),
+            class: loader.key("keys.class", file.keys.class, 'c'),
+            school: loader.key("keys.school", file.keys.school, 's'),
+            level: loader.key("keys.level", file.keys.level, 'l'),
+            results: loader.key("keys.results", file.keys.results, 'r'),
+            shrink: loader.key("keys.shrink", file.keys.shrink, '<'),
+            grow: loader.key("keys.grow", file.keys.grow, '>'),
+        };
+        let bindings = keys.bindings();
+        for (i, (_, name, key)) in bindings.iter().enumerate() {
+            if let Some((_, other, _)) = bindings[..i].iter().find(|(_, _, other)| other == key) {
+                loader.errors.push(format!("keys.{} and keys.{} are both bound to `{}`", other, name, key));
+            }
+        }
+
+        if !loader.errors.is_empty() {
+            return Err(loader.errors);
+        }
+        Ok(Config {
+            file: file_path,
+            database,
+            imports,
+            templates_dir,
+            log_file,
+            log_level,
+            tick_rate,
+            layouts,
+            theme,
+            keys,
+            settings: loader.settings,
+        })
+    }
+
+    /// Problems that only show up once the configured files are used, like
+    /// import paths that don't exist.
+    pub fn missing_files(&self) -> Vec<String> {
+        let files = [
+            ("imports.spells", &self.imports.spells, false),
+            ("imports.sources_dir", &self.imports.sources_dir, true),
+            ("imports.equipment", &self.imports.equipment, false),
+            ("imports.magic_items", &self.imports.magic_items, false),
+            ("imports.conditions", &self.imports.conditions, false),
+            ("imports.rules", &self.imports.rules, false),
+            ("imports.levels", &self.imports.levels, false),
+            ("export.templates_dir", &self.templates_dir, true),
+        ];
+        let mut problems: Vec<String> = files.iter()
+            .filter_map(|(name, path, dir)| {
+                let path = path.as_ref()?;
+                match (path.exists(), *dir) {
+                    (false, _) => Some(format!("{}: {} doesn't exist", name, path.display())),
+                    (true, true) if !path.is_dir() => Some(format!("{}: {} isn't a directory", name, path.display())),
+                    (true, false) if path.is_dir() => Some(format!("{}: {} is a directory", name, path.display())),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let log_dir = self.log_file.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if let Some(dir) = log_dir.filter(|dir| !dir.is_dir()) {
+            problems.push(format!("log.file: {} doesn't exist", dir.display()));
+        }
+        problems
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Makes `config` the one `get` returns, once it has been loaded in `main`.
+pub fn init(config: Config) {
+    CONFIG.set(config).ok();
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("The config is loaded before anything uses it")
+}
+
+pub fn theme() -> &'static Theme {
+    &get().theme
+}
+
+/// Loads the config with every setting at its default, for tests that draw
+/// or handle keys. Only the first call does anything.
+#[cfg(test)]
+pub fn init_default() {
+    use clap::Parser;
+
+    static DEFAULT: std::sync::Once = std::sync::Once::new();
+    DEFAULT.call_once(|| {
+        let path = env::temp_dir().join(format!("dnd_tui-config-{}.toml", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let cli = Cli::parse_from(["dnd_tui", "--config", path.to_str().unwrap()]);
+        init(Config::load(&cli).expect("The default config is valid"));
+        fs::remove_file(&path).ok();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::style::Color;
+
+    use super::color;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(color("Light Blue"), Ok(Color::LightBlue));
+        assert_eq!(color("dark-gray"), Ok(Color::DarkGray));
+        assert_eq!(color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+        assert_eq!(color("208"), Ok(Color::Indexed(208)));
+    }
+
+    #[test]
+    fn rejects_bad_colors() {
+        assert!(color("#ff80zz").is_err());
+        assert!(color("#ff80é").is_err());
+        assert!(color("#0é000").is_err());
+        assert!(color("256").is_err());
+        assert!(color("mauve").is_err());
+    }
+}
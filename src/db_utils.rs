@@ -1,28 +1,46 @@
+//! Connecting to the spell database, querying it and importing into it.
+//!
+//! Queries expect the schema to be in place and panic on database errors,
+//! as they only fail when the database itself is broken. Imports read
+//! files that may well be wrong, and report errors through `ImportError`.
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::expression::dsl::max;
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
-use dotenv::dotenv;
-use std::env;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
-use std::convert::TryFrom;
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::Read;
 use log::info;
 
-use serde::{Deserialize, Serialize};
-use serde_json::{Result, Value};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::importers::{field, integer, parse_spells, string, SpellFormat, SpellRecord};
+use crate::models::{Class, ItemCategory, Rarity, School};
 
-use crate::models::{Class, School};
+embed_migrations!();
 
-pub fn establish_connection() -> SqliteConnection {
-    dotenv().ok();
+/// The SRD spell list shipped inside the binary, used whenever `SPELLS_JSON`
+/// isn't set.
+const BUNDLED_SPELLS: &[u8] = include_bytes!("../data/5e-SRD-Spells.json.gz");
 
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
-    SqliteConnection::establish(&database_url)
-        .expect(&format!("Error connecting to {}", database_url))
+/// Opens the SQLite database at `database_url`, creating it if needed, and
+/// runs any migrations it is missing.
+pub fn connect(database_url: &str) -> Result<SqliteConnection, String> {
+    let conn = SqliteConnection::establish(database_url)
+        .map_err(|error| format!("Error connecting to {}: {}", database_url, error))?;
+    embedded_migrations::run(&conn)
+        .map_err(|error| format!("Failed to run database migrations: {}", error))?;
+    Ok(conn)
 }
 
-use super::schema::spells;
+use super::schema::{campaign_sources, campaigns, class_levels, equipment, favorites, glossary,
+                    imports, magic_items, pane_sizes, sources, spells};
 
 #[derive(Insertable)]
 #[table_name="spells"]
@@ -44,9 +62,11 @@ struct NewSpell<'a> {
     school: &'a str,
     classes: &'a str,
     subclasses: &'a str,
+    source_id: i32,
 }
 
-#[derive(Queryable, Clone)]
+/// A spell as stored, along with the name of the source it came from.
+#[derive(Queryable, Clone, Default, Serialize)]
 pub struct Spell {
     pub id: i32,
     pub name: String,
@@ -65,85 +85,116 @@ pub struct Spell {
     pub school: String,
     pub classes: String,
     pub subclasses: String,
+    pub source_id: i32,
+    pub source: String,
 }
 
-fn insert_spell(i: usize, spell: &Value, conn: &SqliteConnection) {
-    info!("{}", spell["name"].as_str().unwrap());
+/// The columns a `Spell` is loaded from, which includes the name of the
+/// source it was imported from.
+type SpellColumns = (
+    spells::id,
+    spells::name,
+    spells::description,
+    spells::higher_level,
+    spells::range,
+    spells::verbal,
+    spells::somatic,
+    spells::material,
+    spells::material_text,
+    spells::ritual,
+    spells::duration,
+    spells::concentration,
+    spells::casting_time,
+    spells::level,
+    spells::school,
+    spells::classes,
+    spells::subclasses,
+    spells::source_id,
+    sources::name,
+);
+
+const SPELL_COLUMNS: SpellColumns = (
+    spells::id,
+    spells::name,
+    spells::description,
+    spells::higher_level,
+    spells::range,
+    spells::verbal,
+    spells::somatic,
+    spells::material,
+    spells::material_text,
+    spells::ritual,
+    spells::duration,
+    spells::concentration,
+    spells::casting_time,
+    spells::level,
+    spells::school,
+    spells::classes,
+    spells::subclasses,
+    spells::source_id,
+    sources::name,
+);
+
+fn insert_spell(i: usize, source_id: i32, spell: &SpellRecord, conn: &SqliteConnection)
+-> QueryResult<()> {
+    info!("{}", spell.name);
     diesel::insert_into(spells::table)
         .values(& NewSpell {
             id: i as i32,
-            name: spell["name"].as_str().unwrap(),
-            description: spell["desc"].as_array()
-                .unwrap()
-                .iter()
-                .fold(String::new(), |acc, sentence| acc + sentence.as_str().unwrap())
-                .as_str(),
-            higher_level: Some(match spell["higher_level"] {
-                Value::Null => String::from(""),
-                _ => String::from(spell["higher_level"]
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .fold(String::new(), |acc, sentence| acc + sentence.as_str().unwrap())
-                    .as_str().clone())
-            }.as_str()),
-            range: match spell["range"] {
-                Value::Null => None,
-                _ => Some(spell["range"].as_str().unwrap()),
-            },
-            verbal: spell["components"].as_array()
-                .unwrap()
-                .iter()
-                .any(|comp| comp.as_str().unwrap() == "V"),
-            somatic: spell["components"].as_array()
-                .unwrap()
-                .iter()
-                .any(|comp| comp.as_str().unwrap() == "S"),
-            material: spell["components"].as_array()
-                .unwrap()
-                .iter()
-                .any(|comp| comp.as_str().unwrap() == "M"),
-            material_text: match spell["material"] {
-                Value::Null => None,
-                _ => Some(spell["material"].as_str().unwrap()),
-            },
-            ritual: spell["ritual"].as_bool().unwrap(),
-            duration: spell["duration"].as_str().unwrap(),
-            concentration: spell["concentration"].as_bool().unwrap(),
-            casting_time: spell["casting_time"].as_str().unwrap(),
-            level: spell["level"].as_i64().unwrap() as i32,
-            school: String::from(spell["school"]
-                .as_object()
-                .unwrap()["name"]
-                .as_str()
-                .unwrap()).to_lowercase().as_str(),
-            classes: spell["classes"]
-                .as_array()
-                .unwrap()
-                .iter()
-                .fold(String::new(), |acc, class| {
-                    acc + "," + class.as_object().unwrap()["name"].as_str().unwrap()
-                }).to_lowercase().as_str(),
+            name: &spell.name,
+            description: &spell.description,
+            higher_level: spell.higher_level.as_deref(),
+            range: spell.range.as_deref(),
+            verbal: spell.verbal,
+            somatic: spell.somatic,
+            material: spell.material,
+            material_text: spell.material_text.as_deref(),
+            ritual: spell.ritual,
+            duration: &spell.duration,
+            concentration: spell.concentration,
+            casting_time: &spell.casting_time,
+            level: spell.level,
+            school: &spell.school,
+            classes: &spell.classes,
             subclasses: "",
+            source_id,
         }
     )
-    .execute(conn)
-    .expect("FAILED");
+    .execute(conn)?;
+    Ok(())
 }
 
-#[derive(Default)]
+/// Filters for `query_spell`. Unset filters match every spell.
+#[derive(Default, Serialize)]
 pub struct Query {
     pub class: Option<Class>,
     pub school: Option<School>,
     pub level: Option<i32>,
+    pub character_level: Option<i32>,
+    pub name: Option<String>,
+    pub ritual: Option<bool>,
+    pub concentration: Option<bool>,
+}
+
+/// A LIKE pattern for text containing `text`, with the wildcards in it
+/// escaped by `\` so they match themselves.
+fn containing(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
 }
 
+/// The spells from the current campaign's active sources that match every
+/// filter in `spell_query`.
 pub fn query_spell(spell_query: &Query, conn: &SqliteConnection) -> Vec<Spell> {
-    let mut query = spells::table.into_boxed();
+    let mut query = spells::table
+        .inner_join(sources::table)
+        .select(SPELL_COLUMNS)
+        .filter(spells::source_id.ne_all(inactive_sources(conn)))
+        .into_boxed();
 
     if let Some(class) =  spell_query.class {
         query = query.filter(
-            spells::classes.like(format!("%{}%", Into::<String>::into(class)))
+            spells::classes.like(containing(&Into::<String>::into(class))).escape('\\')
         );
     }
 
@@ -154,9 +205,34 @@ pub fn query_spell(spell_query: &Query, conn: &SqliteConnection) -> Vec<Spell> {
     }
 
     if let Some(level) = spell_query.level {
-        if level > 0 {
+        query = query.filter(
+            spells::level.eq(level)
+        );
+    }
+
+    if let Some(name) = &spell_query.name {
+        query = query.filter(
+            spells::name.like(containing(name)).escape('\\')
+        );
+    }
+
+    if let Some(ritual) = spell_query.ritual {
+        query = query.filter(
+            spells::ritual.eq(ritual)
+        );
+    }
+
+    if let Some(concentration) = spell_query.concentration {
+        query = query.filter(
+            spells::concentration.eq(concentration)
+        );
+    }
+
+    // A character can only cast spells of levels they have slots for
+    if let (Some(class), Some(character_level)) = (spell_query.class, spell_query.character_level) {
+        if let Some(class_level) = query_class_level(class, character_level, conn) {
             query = query.filter(
-                spells::level.eq(level as i32)
+                spells::level.le(class_level.max_spell_level())
             );
         }
     }
@@ -165,14 +241,1076 @@ pub fn query_spell(spell_query: &Query, conn: &SqliteConnection) -> Vec<Spell> {
         .expect("Failed to query for spell")
 }
 
-pub fn build_db(conn: &SqliteConnection) {
-    let json_path = env::var("SPELLS_JSON")
-        .expect("Spells json path must be set");
+/// Finds the spell called `name`, or the only spell whose name contains it.
+pub fn find_spell(name: &str, conn: &SqliteConnection) -> Result<Spell, String> {
+    let mut spells = query_spell(&Query {
+        name: Some(name.to_owned()),
+        ..Query::default()
+    }, conn);
+
+    if let Some(i) = spells.iter().position(|spell| spell.name.eq_ignore_ascii_case(name)) {
+        return Ok(spells.swap_remove(i));
+    }
+    match spells.len() {
+        0 => Err(format!("No spell matches \"{}\"", name)),
+        1 => Ok(spells.remove(0)),
+        _ => Err(format!(
+            "\"{}\" matches several spells: {}",
+            name,
+            spells.iter().map(|spell| spell.name.as_str()).collect::<Vec<&str>>().join(", ")
+        )),
+    }
+}
+
+/// The spell with the given id, whether or not its source is active.
+pub fn load_spell(id: i32, conn: &SqliteConnection) -> Option<Spell> {
+    spells::table
+        .inner_join(sources::table)
+        .select(SPELL_COLUMNS)
+        .filter(spells::id.eq(id))
+        .first(conn)
+        .optional()
+        .expect("Failed to query for spell")
+}
+
+/// A named set of spells, like the SRD or a homebrew file. Spells from
+/// sources the current campaign leaves out are left out of queries.
+#[derive(Clone)]
+pub struct Source {
+    pub id: i32,
+    pub name: String,
+    /// Whether the current campaign allows the source
+    pub active: bool,
+}
+
+/// Every source, in the order they were created.
+pub fn load_sources(conn: &SqliteConnection) -> Vec<Source> {
+    let inactive = inactive_sources(conn);
+    sources::table.order(sources::id)
+        .load::<(i32, String)>(conn)
+        .expect("Failed to load sources")
+        .into_iter()
+        .map(|(id, name)| Source {
+            id,
+            name,
+            active: !inactive.contains(&id),
+        })
+        .collect()
+}
+
+/// A set of allowed sources, since different campaigns allow different
+/// books. One campaign is current, and it decides which spells queries see.
+#[derive(Queryable, Clone)]
+pub struct Campaign {
+    pub id: i32,
+    pub name: String,
+    pub current: bool,
+}
+
+/// Every campaign, in the order they were created.
+pub fn load_campaigns(conn: &SqliteConnection) -> Vec<Campaign> {
+    campaigns::table.order(campaigns::id)
+        .load(conn)
+        .expect("Failed to load campaigns")
+}
+
+pub fn current_campaign(conn: &SqliteConnection) -> Campaign {
+    campaigns::table.filter(campaigns::current.eq(true))
+        .first(conn)
+        .expect("Failed to load the current campaign")
+}
+
+/// The campaign named `name`, matched case-insensitively.
+pub fn find_campaign(name: &str, conn: &SqliteConnection) -> Result<Campaign, String> {
+    load_campaigns(conn)
+        .into_iter()
+        .find(|campaign| campaign.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No campaign is named \"{}\"", name))
+}
+
+/// Creates a campaign that allows every source.
+pub fn add_campaign(name: &str, conn: &SqliteConnection) -> Result<Campaign, String> {
+    if find_campaign(name, conn).is_ok() {
+        return Err(format!("A campaign named \"{}\" already exists", name));
+    }
+    let id = campaigns::table.select(max(campaigns::id))
+        .first::<Option<i32>>(conn)
+        .expect("Failed to query for campaign")
+        .map_or(0, |id| id + 1);
+    diesel::insert_into(campaigns::table)
+        .values((campaigns::id.eq(id), campaigns::name.eq(name), campaigns::current.eq(false)))
+        .execute(conn)
+        .expect("Failed to create campaign");
+    Ok(Campaign {
+        id,
+        name: name.to_owned(),
+        current: false,
+    })
+}
+
+/// Deletes a campaign other than the current one, along with its sources.
+pub fn remove_campaign(campaign: &Campaign, conn: &SqliteConnection) -> Result<(), String> {
+    if campaign.current {
+        return Err(format!("\"{}\" is the current campaign", campaign.name));
+    }
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let sources = campaign_sources::table
+            .filter(campaign_sources::campaign_id.eq(campaign.id));
+        diesel::delete(sources).execute(conn)?;
+        diesel::delete(campaigns::table.find(campaign.id)).execute(conn)?;
+        Ok(())
+    })
+    .expect("Failed to remove campaign");
+    Ok(())
+}
+
+/// Makes the campaign with `campaign_id` the one whose sources queries use.
+pub fn set_current_campaign(campaign_id: i32, conn: &SqliteConnection) {
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(campaigns::table)
+            .set(campaigns::current.eq(false))
+            .execute(conn)?;
+        diesel::update(campaigns::table.find(campaign_id))
+            .set(campaigns::current.eq(true))
+            .execute(conn)?;
+        Ok(())
+    })
+    .expect("Failed to change campaign");
+}
+
+/// Ids of the sources the current campaign leaves out.
+fn inactive_sources(conn: &SqliteConnection) -> Vec<i32> {
+    campaign_sources::table.inner_join(campaigns::table)
+        .filter(campaigns::current.eq(true))
+        .filter(campaign_sources::active.eq(false))
+        .select(campaign_sources::source_id)
+        .load(conn)
+        .expect("Failed to load the campaign's sources")
+}
+
+/// How many spells `source` holds.
+pub fn count_spells(source: &Source, conn: &SqliteConnection) -> i64 {
+    spells::table.filter(spells::source_id.eq(source.id))
+        .count()
+        .get_result(conn)
+        .expect("Failed to count spells")
+}
+
+/// Includes or leaves out the source's spells in the current campaign.
+pub fn set_source_active(source_id: i32, active: bool, conn: &SqliteConnection) {
+    diesel::replace_into(campaign_sources::table)
+        .values((
+            campaign_sources::campaign_id.eq(current_campaign(conn).id),
+            campaign_sources::source_id.eq(source_id),
+            campaign_sources::active.eq(active),
+        ))
+        .execute(conn)
+        .expect("Failed to update source");
+}
+
+/// Names of the spells marked as favorites. They're kept by name so they
+/// survive a reimport.
+pub fn load_favorites(conn: &SqliteConnection) -> Vec<String> {
+    favorites::table.select(favorites::name)
+        .order(favorites::name)
+        .load(conn)
+        .expect("Failed to load favorites")
+}
+
+/// Marks or unmarks the spell as a favorite.
+pub fn set_favorite(name: &str, favorite: bool, conn: &SqliteConnection) {
+    if favorite {
+        diesel::replace_into(favorites::table)
+            .values(favorites::name.eq(name))
+            .execute(conn)
+            .expect("Failed to add favorite");
+    } else {
+        diesel::delete(favorites::table.find(name))
+            .execute(conn)
+            .expect("Failed to remove favorite");
+    }
+}
+
+/// Pane sizes the user has changed, as percentages keyed by where the pane
+/// is in its layout.
+pub fn load_pane_sizes(conn: &SqliteConnection) -> HashMap<String, u16> {
+    pane_sizes::table.select((pane_sizes::pane, pane_sizes::percent))
+        .load::<(String, i32)>(conn)
+        .expect("Failed to load pane sizes")
+        .into_iter()
+        .map(|(pane, percent)| (pane, percent as u16))
+        .collect()
+}
+
+pub fn save_pane_sizes(sizes: &[(String, u16)], conn: &SqliteConnection) {
+    for (pane, percent) in sizes {
+        diesel::replace_into(pane_sizes::table)
+            .values((pane_sizes::pane.eq(pane), pane_sizes::percent.eq(*percent as i32)))
+            .execute(conn)
+            .expect("Failed to save pane size");
+    }
+}
+
+/// Looks up a source by name, creating it when it doesn't exist yet. New
+/// sources are active in every campaign.
+fn source_id(name: &str, conn: &SqliteConnection) -> QueryResult<i32> {
+    let existing = sources::table.filter(sources::name.eq(name))
+        .select(sources::id)
+        .first::<i32>(conn)
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let id = sources::table.select(max(sources::id))
+        .first::<Option<i32>>(conn)?
+        .map_or(0, |id| id + 1);
+    diesel::insert_into(sources::table)
+        .values((sources::id.eq(id), sources::name.eq(name)))
+        .execute(conn)?;
+    Ok(id)
+}
+
+/// What `import_spells` does with spells already in the source.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Every spell from the source is replaced by the imported ones
+    Replace,
+    /// Imported spells replace those of the same name, the rest are kept
+    Merge,
+}
+
+/// What an import changed, or would change for a dry run.
+#[derive(Default)]
+pub struct ImportCounts {
+    pub added: usize,
+    pub replaced: usize,
+    pub removed: usize,
+}
+
+fn count_changes(spells: &[SpellRecord], existing: &[String], mode: ImportMode) -> ImportCounts {
+    let replaced = spells.iter()
+        .filter(|spell| existing.contains(&spell.name))
+        .count();
+    ImportCounts {
+        added: spells.len() - replaced,
+        replaced,
+        removed: match mode {
+            ImportMode::Replace => existing.iter()
+                .filter(|name| !spells.iter().any(|spell| spell.name == **name))
+                .count(),
+            ImportMode::Merge => 0,
+        },
+    }
+}
+
+/// Imports `spells` into the source named `source`, which is created if
+/// needed.
+pub fn import_spells(
+    spells: &[SpellRecord],
+    source: &str,
+    mode: ImportMode,
+    on_progress: &mut OnProgress,
+    conn: &SqliteConnection
+) -> Result<ImportCounts, ImportError> {
+    let source_id = source_id(source, conn)?;
+    let existing: Vec<String> = spells::table.filter(spells::source_id.eq(source_id))
+        .select(spells::name)
+        .load(conn)?;
+    let counts = count_changes(spells, &existing, mode);
+
+    match mode {
+        ImportMode::Replace => {
+            diesel::delete(spells::table.filter(spells::source_id.eq(source_id)))
+                .execute(conn)?;
+        },
+        ImportMode::Merge => {
+            let names: Vec<&str> = spells.iter().map(|spell| spell.name.as_str()).collect();
+            diesel::delete(spells::table
+                .filter(spells::source_id.eq(source_id))
+                .filter(spells::name.eq_any(names)))
+                .execute(conn)?;
+        },
+    }
+
+    let first_id = spells::table.select(max(spells::id))
+        .first::<Option<i32>>(conn)?
+        .map_or(0, |id| id + 1) as usize;
+    for (i, spell) in spells.iter().enumerate() {
+        report(on_progress, "spells", &spell.name, i, spells.len())?;
+        insert_spell(first_id + i, source_id, spell, conn)?;
+    }
+    Ok(counts)
+}
+
+#[derive(Insertable)]
+#[table_name="equipment"]
+struct NewEquipment {
+    id: i32,
+    name: String,
+    category: String,
+    subcategory: Option<String>,
+    cost: Option<String>,
+    weight: Option<f32>,
+    damage: Option<String>,
+    armor_class: Option<String>,
+    properties: String,
+    description: String,
+}
+
+/// Mundane gear, weapons and armor from `EQUIPMENT_JSON`.
+#[derive(Queryable, Clone)]
+pub struct Equipment {
+    pub id: i32,
+    pub name: String,
+    pub category: String,
+    pub subcategory: Option<String>,
+    pub cost: Option<String>,
+    pub weight: Option<f32>,
+    pub damage: Option<String>,
+    pub armor_class: Option<String>,
+    pub properties: String,
+    pub description: String,
+}
+
+#[derive(Insertable)]
+#[table_name="magic_items"]
+struct NewMagicItem {
+    id: i32,
+    name: String,
+    category: String,
+    rarity: String,
+    attunement: bool,
+    description: String,
+}
+
+/// A magic item from `MAGIC_ITEMS_JSON`.
+#[derive(Queryable, Clone)]
+pub struct MagicItem {
+    pub id: i32,
+    pub name: String,
+    pub category: String,
+    pub rarity: String,
+    pub attunement: bool,
+    pub description: String,
+}
+
+/// Mundane equipment and magic items share a single browser, so results
+/// from both tables are handed around as one type.
+#[derive(Clone)]
+pub enum Item {
+    Equipment(Equipment),
+    Magic(MagicItem),
+}
+
+impl Item {
+    pub fn name(&self) -> &str {
+        match self {
+            Item::Equipment(item) => &item.name,
+            Item::Magic(item) => &item.name,
+        }
+    }
+}
+
+/// Descriptions are stored one paragraph, list item or table row per line
+/// so their structure survives for rendering.
+fn join_desc(desc: &Value) -> String {
+    match desc {
+        Value::Array(lines) => lines.iter()
+            .filter_map(|line| line.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// The lowercased name of the object under `key`, such as an item's
+/// `equipment_category`.
+fn named(record: &Value, key: &str) -> Result<String, String> {
+    string(field(record, key)?, "name")
+        .map(|name| name.to_lowercase())
+        .map_err(|_| format!("`{}` should be named", key))
+}
+
+fn equipment_record(i: usize, item: &Value) -> Result<NewEquipment, String> {
+    let subcategory = ["category_range", "armor_category", "tool_category", "vehicle_category"]
+        .iter()
+        .find_map(|key| item[*key].as_str())
+        .or_else(|| item["gear_category"]["name"].as_str());
+    let cost = match item.get("cost") {
+        Some(Value::Null) | None => None,
+        Some(cost) => Some(format!("{} {}", field(cost, "quantity")?, string(cost, "unit")?)),
+    };
+    let damage = match item.get("damage") {
+        Some(Value::Null) | None => None,
+        Some(damage) => Some(format!(
+            "{} {}",
+            string(damage, "damage_dice")?,
+            named(damage, "damage_type")?
+        )),
+    };
+    let armor_class = match item.get("armor_class") {
+        Some(Value::Null) | None => None,
+        Some(armor_class) => Some(format!(
+            "{}{}",
+            field(armor_class, "base")?,
+            match (armor_class["dex_bonus"].as_bool(), armor_class["max_bonus"].as_i64()) {
+                (Some(true), Some(max)) => format!(" + Dex modifier (max {})", max),
+                (Some(true), None) => String::from(" + Dex modifier"),
+                _ => String::new(),
+            }
+        )),
+    };
+    let properties = match item.get("properties") {
+        Some(Value::Array(properties)) => properties.iter()
+            .map(|property| string(property, "name"))
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|_| String::from("`properties` should hold named properties"))?
+            .join(", "),
+        _ => String::new(),
+    };
+
+    Ok(NewEquipment {
+        id: i as i32,
+        name: string(item, "name")?,
+        category: named(item, "equipment_category")?,
+        subcategory: subcategory.map(str::to_owned),
+        cost,
+        weight: item["weight"].as_f64().map(|weight| weight as f32),
+        damage,
+        armor_class,
+        properties,
+        description: join_desc(&item["desc"]),
+    })
+}
+
+fn magic_item_record(i: usize, item: &Value) -> Result<NewMagicItem, String> {
+    Ok(NewMagicItem {
+        id: i as i32,
+        name: string(item, "name")?,
+        category: named(item, "equipment_category")?,
+        rarity: named(item, "rarity")?,
+        attunement: item["desc"][0]
+            .as_str()
+            .is_some_and(|line| line.contains("requires attunement")),
+        description: join_desc(&item["desc"]),
+    })
+}
+
+/// Filters for `query_items`. Unset filters match every item.
+#[derive(Default)]
+pub struct ItemQuery {
+    pub category: Option<ItemCategory>,
+    pub rarity: Option<Rarity>,
+    pub attunement: Option<bool>,
+}
+
+/// Equipment and magic items matching every filter in `item_query`.
+pub fn query_items(item_query: &ItemQuery, conn: &SqliteConnection) -> Vec<Item> {
+    let mut items = Vec::new();
+
+    // Mundane equipment has neither a rarity nor attunement, so it only
+    // matches when those filters are unset or explicitly exclude attunement
+    if item_query.rarity.is_none() && item_query.attunement != Some(true) {
+        let mut query = equipment::table.into_boxed();
+
+        if let Some(category) = item_query.category {
+            query = query.filter(
+                equipment::category.eq(Into::<String>::into(category))
+            );
+        }
+
+        items.extend(
+            query.load::<Equipment>(conn)
+                .expect("Failed to query for equipment")
+                .into_iter()
+                .map(Item::Equipment)
+        );
+    }
+
+    let mut query = magic_items::table.into_boxed();
+
+    if let Some(category) = item_query.category {
+        query = query.filter(
+            magic_items::category.eq(Into::<String>::into(category))
+        );
+    }
+
+    if let Some(rarity) = item_query.rarity {
+        query = query.filter(
+            magic_items::rarity.eq(Into::<String>::into(rarity))
+        );
+    }
+
+    if let Some(attunement) = item_query.attunement {
+        query = query.filter(
+            magic_items::attunement.eq(attunement)
+        );
+    }
+
+    items.extend(
+        query.load::<MagicItem>(conn)
+            .expect("Failed to query for magic items")
+            .into_iter()
+            .map(Item::Magic)
+    );
+    items
+}
+
+#[derive(Insertable)]
+#[table_name="glossary"]
+struct NewGlossaryEntry {
+    id: i32,
+    term: String,
+    kind: &'static str,
+    definition: String,
+}
+
+/// A condition or rule section, linked from spell descriptions that
+/// mention its term.
+#[derive(Queryable, Clone)]
+pub struct GlossaryEntry {
+    pub id: i32,
+    pub term: String,
+    pub kind: String,
+    pub definition: String,
+}
+
+fn glossary_record(i: usize, kind: &'static str, entry: &Value)
+-> Result<NewGlossaryEntry, String> {
+    let term = string(entry, "name")?;
+    // Rule sections are a single markdown string which repeats the section
+    // name as a heading, conditions are a list of bullet points
+    let definition = match &entry["desc"] {
+        Value::String(desc) => desc.trim_start_matches(&format!("## {}", term))
+            .trim()
+            .to_owned(),
+        desc => join_desc(desc),
+    };
+
+    Ok(NewGlossaryEntry {
+        id: i as i32,
+        term,
+        kind,
+        definition,
+    })
+}
+
+/// Every condition and rule section.
+pub fn load_glossary(conn: &SqliteConnection) -> Vec<GlossaryEntry> {
+    glossary::table.load(conn)
+        .expect("Failed to load glossary")
+}
+
+#[derive(Insertable)]
+#[table_name="class_levels"]
+struct NewClassLevel {
+    id: i32,
+    class: String,
+    level: i32,
+    cantrips_known: Option<i32>,
+    spells_known: Option<i32>,
+    slots_1: i32,
+    slots_2: i32,
+    slots_3: i32,
+    slots_4: i32,
+    slots_5: i32,
+    slots_6: i32,
+    slots_7: i32,
+    slots_8: i32,
+    slots_9: i32,
+}
+
+/// A class's spellcasting at one character level.
+#[derive(Queryable, Clone)]
+pub struct ClassLevel {
+    pub id: i32,
+    pub class: String,
+    pub level: i32,
+    pub cantrips_known: Option<i32>,
+    pub spells_known: Option<i32>,
+    pub slots_1: i32,
+    pub slots_2: i32,
+    pub slots_3: i32,
+    pub slots_4: i32,
+    pub slots_5: i32,
+    pub slots_6: i32,
+    pub slots_7: i32,
+    pub slots_8: i32,
+    pub slots_9: i32,
+}
+
+impl ClassLevel {
+    pub fn slots(&self) -> [i32; 9] {
+        [
+            self.slots_1,
+            self.slots_2,
+            self.slots_3,
+            self.slots_4,
+            self.slots_5,
+            self.slots_6,
+            self.slots_7,
+            self.slots_8,
+            self.slots_9,
+        ]
+    }
+
+    /// The highest spell level castable at this class level, where 0 means
+    /// cantrips only.
+    pub fn max_spell_level(&self) -> i32 {
+        self.slots()
+            .iter()
+            .rposition(|slots| *slots > 0)
+            .map_or(0, |i| i as i32 + 1)
+    }
+}
+
+fn class_level_record(i: usize, class_level: &Value) -> Result<NewClassLevel, String> {
+    let spellcasting = &class_level["spellcasting"];
+    let slots = |level: usize| {
+        spellcasting[format!("spell_slots_level_{}", level)]
+            .as_i64()
+            .unwrap_or(0) as i32
+    };
+
+    Ok(NewClassLevel {
+        id: i as i32,
+        class: named(class_level, "class")?,
+        level: integer(class_level, "level")?,
+        cantrips_known: spellcasting["cantrips_known"].as_i64().map(|known| known as i32),
+        spells_known: spellcasting["spells_known"].as_i64().map(|known| known as i32),
+        slots_1: slots(1),
+        slots_2: slots(2),
+        slots_3: slots(3),
+        slots_4: slots(4),
+        slots_5: slots(5),
+        slots_6: slots(6),
+        slots_7: slots(7),
+        slots_8: slots(8),
+        slots_9: slots(9),
+    })
+}
+
+/// Every level of `class`, from first to twentieth.
+pub fn query_class_levels(class: Class, conn: &SqliteConnection) -> Vec<ClassLevel> {
+    class_levels::table
+        .filter(class_levels::class.eq(Into::<String>::into(class)))
+        .order(class_levels::level)
+        .load(conn)
+        .expect("Failed to query for class levels")
+}
+
+/// `class` at character `level`, if the levels were imported.
+pub fn query_class_level(class: Class, level: i32, conn: &SqliteConnection) -> Option<ClassLevel> {
+    class_levels::table
+        .filter(class_levels::class.eq(Into::<String>::into(class)))
+        .filter(class_levels::level.eq(level))
+        .first(conn)
+        .optional()
+        .expect("Failed to query for class level")
+}
+
+/// A json file read for import along with where it came from, so the import
+/// can be recorded.
+struct JsonSource {
+    path: String,
+    hash: String,
+    values: Vec<Value>,
+}
+
+/// Reads a list of records, which may be wrapped in an object under
+/// `results` (as Open5e pages are) or `spells` (as the JSON export is).
+fn parse_json(path: String, bytes: &[u8]) -> Result<JsonSource, ImportError> {
+    let value: Value = serde_json::from_slice(bytes)
+        .map_err(|error| ImportError::Read { path: path.clone(), error: error.to_string() })?;
+    let values = match value {
+        Value::Array(values) => values,
+        Value::Object(mut object) => match object.remove("results").or_else(|| object.remove("spells")) {
+            Some(Value::Array(values)) => values,
+            _ => return Err(ImportError::Read { path, error: String::from("expected a list of records") }),
+        },
+        _ => return Err(ImportError::Read { path, error: String::from("expected a list of records") }),
+    };
+    Ok(JsonSource {
+        hash: format!("{:x}", Sha256::digest(bytes)),
+        path,
+        values,
+    })
+}
+
+fn read_json(path: &Path) -> Result<JsonSource, ImportError> {
+    let path = path.display().to_string();
+    let bytes = fs::read(&path)
+        .map_err(|error| ImportError::Read { path: path.clone(), error: error.to_string() })?;
+    parse_json(path, &bytes)
+}
+
+fn read_bundled_spells() -> JsonSource {
+    let mut bytes = Vec::new();
+    GzDecoder::new(BUNDLED_SPELLS)
+        .read_to_end(&mut bytes)
+        .expect("Failed to read bundled spells");
+    parse_json(String::from("(bundled) data/5e-SRD-Spells.json.gz"), &bytes)
+        .expect("Failed to read bundled spells")
+}
+
+/// The bundled SRD spells in the same format as the 5e-database json.
+pub fn bundled_spells() -> Vec<Value> {
+    read_bundled_spells().values
+}
+
+/// Whether nothing has been imported yet.
+pub fn is_empty(conn: &SqliteConnection) -> bool {
+    spells::table.count()
+        .get_result::<i64>(conn)
+        .expect("Failed to count spells") == 0
+}
+
+/// The attribution the SRD's license requires wherever its content is used.
+pub const SRD_ATTRIBUTION: &str = "This work includes material taken from the System \
+Reference Document 5.1 (\"SRD 5.1\") by Wizards of the Coast LLC and available at \
+https://dnd.wizards.com/resources/systems-reference-document. The SRD 5.1 is licensed \
+under the Creative Commons Attribution 4.0 International License available at \
+https://creativecommons.org/licenses/by/4.0/legalcode.";
+
+#[derive(Insertable)]
+#[table_name="imports"]
+struct NewImport<'a> {
+    id: i32,
+    imported_at: NaiveDateTime,
+    importer_version: &'a str,
+    kind: &'a str,
+    source_path: &'a str,
+    content_hash: &'a str,
+    records: i32,
+}
+
+/// One file read by a `build_db` run. Every file of a run shares the same
+/// `imported_at`.
+#[derive(Queryable, Clone)]
+pub struct Import {
+    pub id: i32,
+    pub imported_at: NaiveDateTime,
+    pub importer_version: String,
+    pub kind: String,
+    pub source_path: String,
+    pub content_hash: String,
+    pub records: i32,
+}
+
+/// Every recorded import, most recent first.
+pub fn load_imports(conn: &SqliteConnection) -> Vec<Import> {
+    imports::table.order((imports::imported_at.desc(), imports::id))
+        .load(conn)
+        .expect("Failed to load imports")
+}
+
+/// How far an import has got through the file it is reading.
+pub struct Progress {
+    pub kind: &'static str,
+    pub name: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Called before each record is imported. Returning `false` cancels the
+/// import.
+pub type OnProgress<'a> = dyn FnMut(Progress) -> bool + 'a;
+
+/// Why an import stopped. Nothing is written when an import fails.
+#[derive(Debug)]
+pub enum ImportError {
+    Cancelled,
+    Database(diesel::result::Error),
+    Read { path: String, error: String },
+    /// Records in the file at `path` that couldn't be read, one description
+    /// each
+    Invalid { path: String, errors: Vec<String> },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Cancelled => write!(f, "The import was cancelled"),
+            ImportError::Database(error) => write!(f, "Database error: {}", error),
+            ImportError::Read { path, error } => write!(f, "Couldn't read {}: {}", path, error),
+            ImportError::Invalid { path, errors } => {
+                write!(f, "{} has {} invalid record(s):", path, errors.len())?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<diesel::result::Error> for ImportError {
+    fn from(error: diesel::result::Error) -> ImportError {
+        ImportError::Database(error)
+    }
+}
+
+fn report(
+    on_progress: &mut OnProgress,
+    kind: &'static str,
+    name: &str,
+    done: usize,
+    total: usize
+) -> Result<(), ImportError> {
+    let progress = Progress {
+        kind,
+        name: name.to_owned(),
+        done,
+        total,
+    };
+    if on_progress(progress) {
+        Ok(())
+    } else {
+        Err(ImportError::Cancelled)
+    }
+}
+
+/// The source name and spell format of a file in `sources_dir`. A second
+/// extension picks the format, as in `deep-magic.open5e.json` or
+/// `my-table.homebrew.json`; files without one are in the SRD layout.
+fn source_file(path: &Path) -> (String, SpellFormat) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let (name, format) = match stem.rsplit_once('.') {
+        Some((name, "srd")) => (name, SpellFormat::Srd),
+        Some((name, "open5e")) => (name, SpellFormat::Open5e),
+        Some((name, "homebrew")) => (name, SpellFormat::Homebrew),
+        _ => (&*stem, SpellFormat::Srd),
+    };
+    (String::from(name), format)
+}
+
+/// The files `build_db` imports. Spells come from the bundled SRD list
+/// unless `spells` is set, everything else is only imported when given.
+#[derive(Clone, Default)]
+pub struct ImportPaths {
+    /// Spells in the 5e-database SRD layout
+    pub spells: Option<PathBuf>,
+    /// A directory whose `<name>.json` files are each imported as their own
+    /// spell source, in the format named by a second extension if any
+    pub sources_dir: Option<PathBuf>,
+    pub equipment: Option<PathBuf>,
+    pub magic_items: Option<PathBuf>,
+    pub conditions: Option<PathBuf>,
+    pub rules: Option<PathBuf>,
+    pub levels: Option<PathBuf>,
+}
+
+/// Imports every source in `paths`, replacing whatever a previous import
+/// left in the tables it covers. The import runs in a single transaction,
+/// so a cancelled import leaves the database as it was.
+pub fn build_db(
+    conn: &SqliteConnection,
+    paths: &ImportPaths,
+    on_progress: &mut OnProgress
+) -> Result<(), ImportError> {
+    conn.transaction::<_, ImportError, _>(|| {
+        let imported = import_all(paths, on_progress, conn)?;
+        record_imports(&imported, conn)?;
+        Ok(())
+    })
+}
+
+/// Imports the spells in the file at `path` into the source named `source`.
+/// Every record is read before anything is written, and a dry run only
+/// reports what the import would change.
+pub fn import_file(
+    path: &Path,
+    format: SpellFormat,
+    source: &str,
+    mode: ImportMode,
+    dry_run: bool,
+    conn: &SqliteConnection
+) -> Result<ImportCounts, ImportError> {
+    let json = read_json(path)?;
+    let spells = spell_records(&json, format)?;
+
+    if dry_run {
+        let existing: Vec<String> = spells::table.inner_join(sources::table)
+            .filter(sources::name.eq(source))
+            .select(spells::name)
+            .load(conn)?;
+        return Ok(count_changes(&spells, &existing, mode));
+    }
+
+    conn.transaction::<_, ImportError, _>(|| {
+        let counts = import_spells(&spells, source, mode, &mut |_| true, conn)?;
+        let records = spells.len();
+        record_imports(&[("spells", json, records)], conn)?;
+        Ok(counts)
+    })
+}
+
+fn record_imports(imported: &[(&str, JsonSource, usize)], conn: &SqliteConnection)
+-> QueryResult<()> {
+    let imported_at = Utc::now().naive_utc();
+    let first_id = imports::table.select(max(imports::id))
+        .first::<Option<i32>>(conn)?
+        .map_or(0, |id| id + 1);
+
+    for (i, (kind, source, records)) in imported.iter().enumerate() {
+        diesel::insert_into(imports::table)
+            .values(& NewImport {
+                id: first_id + i as i32,
+                imported_at,
+                importer_version: env!("CARGO_PKG_VERSION"),
+                kind,
+                source_path: &source.path,
+                content_hash: &source.hash,
+                records: *records as i32,
+            })
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Reads every record in `values` with `parse`, or describes each one that
+/// couldn't be read as `parse_spells` does.
+fn parse_records<'a, T>(
+    source: &JsonSource,
+    values: impl IntoIterator<Item = &'a Value>,
+    parse: impl Fn(usize, &Value) -> Result<T, String>
+) -> Result<Vec<T>, ImportError> {
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        match parse(i, value) {
+            Ok(record) => records.push(record),
+            Err(error) => errors.push(format!(
+                "record {} ({}): {}",
+                i + 1,
+                value["name"].as_str().or_else(|| value["index"].as_str()).unwrap_or("unnamed"),
+                error
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        Err(ImportError::Invalid { path: source.path.clone(), errors })
+    }
+}
+
+fn spell_records(source: &JsonSource, format: SpellFormat)
+-> Result<Vec<SpellRecord>, ImportError> {
+    parse_spells(&source.values, format)
+        .map_err(|errors| ImportError::Invalid { path: source.path.clone(), errors })
+}
+
+/// Runs every import and returns what kind of data was read from which
+/// file, and how many records it held.
+fn import_all(
+    paths: &ImportPaths,
+    on_progress: &mut OnProgress,
+    conn: &SqliteConnection
+) -> Result<Vec<(&'static str, JsonSource, usize)>, ImportError> {
+    let mut imported = Vec::new();
+
+    let spells = match &paths.spells {
+        Some(json_path) => read_json(json_path)?,
+        None => read_bundled_spells(),
+    };
+    import_spells(&spell_records(&spells, SpellFormat::Srd)?, "SRD", ImportMode::Replace, on_progress, conn)?;
+    let records = spells.values.len();
+    imported.push(("spells", spells, records));
+
+    // Homebrew and third-party spells live in their own files, each named
+    // after the source it holds
+    if let Some(dir) = &paths.sources_dir {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .map_err(|error| ImportError::Read {
+                path: dir.display().to_string(),
+                error: error.to_string(),
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        for path in files {
+            let (source, format) = source_file(&path);
+            let spells = read_json(&path)?;
+            import_spells(&spell_records(&spells, format)?, &source, ImportMode::Replace, on_progress, conn)?;
+            let records = spells.values.len();
+            imported.push(("spells", spells, records));
+        }
+    }
+
+    if let Some(json_path) = &paths.equipment {
+        diesel::delete(equipment::table).execute(conn)?;
+        let items = read_json(json_path)?;
+        let new_items = parse_records(&items, &items.values, equipment_record)?;
+        for (i, item) in new_items.iter().enumerate() {
+            report(on_progress, "equipment", &item.name, i, new_items.len())?;
+            info!("{}", item.name);
+            diesel::insert_into(equipment::table).values(item).execute(conn)?;
+        }
+        let records = items.values.len();
+        imported.push(("equipment", items, records));
+    }
+
+    if let Some(json_path) = &paths.magic_items {
+        diesel::delete(magic_items::table).execute(conn)?;
+        let items = read_json(json_path)?;
+        let new_items = parse_records(&items, &items.values, magic_item_record)?;
+        for (i, item) in new_items.iter().enumerate() {
+            report(on_progress, "magic items", &item.name, i, new_items.len())?;
+            info!("{}", item.name);
+            diesel::insert_into(magic_items::table).values(item).execute(conn)?;
+        }
+        let records = items.values.len();
+        imported.push(("magic items", items, records));
+    }
+
+    if paths.conditions.is_some() || paths.rules.is_some() {
+        diesel::delete(glossary::table).execute(conn)?;
+    }
+
+    let mut entries = 0;
+    for (json_path, kind, plural) in [
+        (&paths.conditions, "condition", "conditions"),
+        (&paths.rules, "rule", "rules"),
+    ] {
+        let json_path = match json_path {
+            Some(json_path) => json_path,
+            None => continue,
+        };
+        let json = read_json(json_path)?;
+        let new_entries = parse_records(&json, &json.values, |i, entry| {
+            glossary_record(entries + i, kind, entry)
+        })?;
+        for (i, entry) in new_entries.iter().enumerate() {
+            report(on_progress, plural, &entry.term, i, new_entries.len())?;
+            info!("{}", entry.term);
+            diesel::insert_into(glossary::table).values(entry).execute(conn)?;
+        }
+        entries += new_entries.len();
+        let records = json.values.len();
+        imported.push((plural, json, records));
+    }
+
+    // Subclass entries only list features, the spellcasting progression
+    // lives on the base class levels
+    if let Some(json_path) = &paths.levels {
+        diesel::delete(class_levels::table).execute(conn)?;
+        let levels = read_json(json_path)?;
+        let base_levels = levels.values.iter()
+            .filter(|class_level| class_level["subclass"].is_null());
+        let new_levels = parse_records(&levels, base_levels, class_level_record)?;
+        for (i, class_level) in new_levels.iter().enumerate() {
+            report(on_progress, "class levels", &class_level.class, i, new_levels.len())?;
+            diesel::insert_into(class_levels::table).values(class_level).execute(conn)?;
+        }
+        let records = new_levels.len();
+        imported.push(("class levels", levels, records));
+    }
 
-    let file = File::open(Path::new(&json_path)).expect("Failed to open json file");
-    let reader = BufReader::new(file);
-    let spells: Vec<Value> = serde_json::from_reader(reader)
-        .expect("Failed to read json");
-    spells.iter().enumerate()
-        .for_each(|(i, spell)| insert_spell(i, spell, conn));
+    Ok(imported)
 }
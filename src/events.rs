@@ -1,95 +1,88 @@
 use std::io;
 use std::sync::mpsc;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
 use std::thread;
 use std::time::Duration;
 
-use termion::event::Key;
+use termion::event::MouseEvent;
 use termion::input::TermRead;
 
+pub use termion::event::Event as Input;
+
+use dnd_tui::db_utils::Progress;
+
 pub enum Event<I> {
     Input(I),
     Tick,
+    Import(ImportEvent),
 }
 
-/// A small event handler that wrap termion input and tick events. Each event
-/// type is handled in its own thread and returned to a common `Receiver`
-pub struct Events {
-    rx: mpsc::Receiver<Event<Key>>,
-    input_handle: thread::JoinHandle<()>,
-    ignore_exit_key: Arc<AtomicBool>,
-    tick_handle: thread::JoinHandle<()>,
+/// Sent by the import worker thread, see `App::start_import`.
+pub enum ImportEvent {
+    Progress(Progress),
+    Finished,
+    Cancelled,
+    Failed(String),
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Config {
-    pub exit_key: Key,
-    pub tick_rate: Duration,
+/// Moves mouse positions to count from 0 like tui's `Rect`s, where termion
+/// counts terminal cells from 1.
+fn from_origin(input: Input) -> Input {
+    match input {
+        Input::Mouse(MouseEvent::Press(button, x, y)) => {
+            Input::Mouse(MouseEvent::Press(button, x.saturating_sub(1), y.saturating_sub(1)))
+        },
+        Input::Mouse(MouseEvent::Release(x, y)) => {
+            Input::Mouse(MouseEvent::Release(x.saturating_sub(1), y.saturating_sub(1)))
+        },
+        Input::Mouse(MouseEvent::Hold(x, y)) => {
+            Input::Mouse(MouseEvent::Hold(x.saturating_sub(1), y.saturating_sub(1)))
+        },
+        input => input,
+    }
 }
 
-impl Default for Config {
-    fn default() -> Config {
-        Config {
-            exit_key: Key::Char('q'),
-            tick_rate: Duration::from_millis(250),
-        }
-    }
+/// A small event handler that wrap termion input and tick events. Each event
+/// type is handled in its own thread and returned to a common `Receiver`
+pub struct Events {
+    rx: mpsc::Receiver<Event<Input>>,
+    tx: mpsc::Sender<Event<Input>>,
 }
 
 impl Events {
-    pub fn new() -> Events {
-        Events::with_config(Config::default())
-    }
-
-    pub fn with_config(config: Config) -> Events {
+    pub fn new(tick_rate: Duration) -> Events {
         let (tx, rx) = mpsc::channel();
-        let ignore_exit_key = Arc::new(AtomicBool::new(false));
-        let input_handle = {
+        // Input keeps being read until the app quits, since keys like `q`
+        // may just be text typed into a prompt
+        {
             let tx = tx.clone();
-            let ignore_exit_key = ignore_exit_key.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if let Err(err) = tx.send(Event::Input(key)) {
-                            eprintln!("{}", err);
-                            return;
-                        }
-                        if !ignore_exit_key.load(Ordering::Relaxed) && key == config.exit_key {
-                            return;
-                        }
+                for input in stdin.events().flatten() {
+                    if let Err(err) = tx.send(Event::Input(from_origin(input))) {
+                        eprintln!("{}", err);
+                        return;
                     }
                 }
-            })
-        };
-        let tick_handle = {
+            });
+        }
+        {
+            let tx = tx.clone();
             thread::spawn(move || loop {
                 if tx.send(Event::Tick).is_err() {
                     break;
                 }
-                thread::sleep(config.tick_rate);
-            })
-        };
-        Events {
-            rx,
-            ignore_exit_key,
-            input_handle,
-            tick_handle,
+                thread::sleep(tick_rate);
+            });
         }
+        Events { rx, tx }
     }
 
-    pub fn next(&self) -> Result<Event<Key>, mpsc::RecvError> {
+    pub fn next(&self) -> Result<Event<Input>, mpsc::RecvError> {
         self.rx.recv()
     }
 
-    pub fn disable_exit_key(&mut self) {
-        self.ignore_exit_key.store(true, Ordering::Relaxed);
-    }
-
-    pub fn enable_exit_key(&mut self) {
-        self.ignore_exit_key.store(false, Ordering::Relaxed);
+    /// A sender for other threads to deliver their own events through.
+    pub fn sender(&self) -> mpsc::Sender<Event<Input>> {
+        self.tx.clone()
     }
 }
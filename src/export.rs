@@ -0,0 +1,402 @@
+use std::fs;
+
+use tui::style::{Modifier, Style};
+
+use serde_json::{json, Value};
+
+use crate::config;
+use dnd_tui::db_utils::{Query, Spell};
+use crate::markup::{self, Block};
+use crate::vtt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+    Csv,
+    Json,
+    Foundry,
+    Roll20,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Markdown,
+        Format::Html,
+        Format::Csv,
+        Format::Json,
+        Format::Foundry,
+        Format::Roll20,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "Markdown",
+            Format::Html => "HTML",
+            Format::Csv => "CSV",
+            Format::Json => "JSON",
+            Format::Foundry => "Foundry VTT",
+            Format::Roll20 => "Roll20",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Foundry => "foundry.json",
+            Format::Roll20 => "roll20.json",
+        }
+    }
+
+    pub fn next(self) -> Format {
+        let i = Format::ALL.iter().position(|format| *format == self).unwrap_or(0);
+        Format::ALL[(i + 1) % Format::ALL.len()]
+    }
+
+    fn escape(self, text: &str) -> String {
+        match self {
+            Format::Html => text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;"),
+            _ => text.to_owned(),
+        }
+    }
+
+    /// Converts a stored description into the format's own markup.
+    fn description(self, text: &str) -> String {
+        match self {
+            Format::Html | Format::Foundry => html(text),
+            _ => markdown(text),
+        }
+    }
+}
+
+/// Loads `<name>.<extension>` from the configured templates directory when
+/// it exists there, otherwise the built-in template.
+fn template(format: Format, name: &str) -> String {
+    let file = format!("{}.{}", name, format.extension());
+    if let Some(dir) = &config::get().templates_dir {
+        if let Ok(template) = fs::read_to_string(dir.join(&file)) {
+            return template;
+        }
+    }
+
+    match (format, name) {
+        (Format::Html, "spell") => include_str!("../templates/spell.html"),
+        (Format::Html, _) => include_str!("../templates/document.html"),
+        (_, "spell") => include_str!("../templates/spell.md"),
+        (_, _) => include_str!("../templates/document.md"),
+    }.to_owned()
+}
+
+/// Fills `{{field}}` placeholders in `template`. The text between
+/// `{{#field}}` and `{{/field}}` is only kept when the field isn't empty.
+/// Unknown placeholders are left as they are.
+pub fn fill(template: &str, fields: &[(&str, String)]) -> String {
+    let value = |name: &str| fields.iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| value.as_str());
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let tag = &rest[start + 2..];
+        let end = match tag.find("}}") {
+            Some(end) => end,
+            None => {
+                rest = &rest[start..];
+                break;
+            },
+        };
+        let name = &tag[..end];
+        let after = &tag[end + 2..];
+
+        if let Some(section) = name.strip_prefix('#') {
+            let close = format!("{{{{/{}}}}}", section);
+            match after.find(&close) {
+                Some(inner_end) => {
+                    if value(section).is_some_and(|value| !value.is_empty()) {
+                        out.push_str(&fill(&after[..inner_end], fields));
+                    }
+                    rest = &after[inner_end + close.len()..];
+                },
+                None => {
+                    out.push_str(&rest[start..start + end + 4]);
+                    rest = after;
+                },
+            }
+        } else {
+            match value(name) {
+                Some(value) => out.push_str(value),
+                None => out.push_str(&rest[start..start + end + 4]),
+            }
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn level_school(spell: &Spell) -> String {
+    let school = spell.school.clone();
+    match spell.level {
+        0 => format!("{}{} cantrip", school[..1].to_uppercase(), &school[1..]),
+        level => format!("{}{}-level {}", level, match level {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        }, school),
+    }
+}
+
+fn components(spell: &Spell) -> String {
+    let mut components = Vec::new();
+    if spell.verbal {
+        components.push(String::from("V"));
+    }
+    if spell.somatic {
+        components.push(String::from("S"));
+    }
+    if spell.material {
+        components.push(match &spell.material_text {
+            Some(material) => format!("M ({})", material),
+            None => String::from("M"),
+        });
+    }
+    components.join(", ")
+}
+
+/// Classes are stored lowercase with a leading comma, like ",bard,wizard".
+pub fn classes(spell: &Spell) -> String {
+    spell.classes.split(',')
+        .filter(|class| !class.is_empty())
+        .map(|class| format!("{}{}", class[..1].to_uppercase(), &class[1..]))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn spell_fields(spell: &Spell, format: Format) -> Vec<(&'static str, String)> {
+    let flag = |set: bool| if set { String::from("yes") } else { String::new() };
+    vec![
+        ("name", format.escape(&spell.name)),
+        ("level", spell.level.to_string()),
+        ("school", format.escape(&spell.school)),
+        ("level_school", format.escape(&level_school(spell))),
+        ("casting_time", format.escape(&spell.casting_time)),
+        ("range", format.escape(spell.range.as_deref().unwrap_or(""))),
+        ("components", format.escape(&components(spell))),
+        ("duration", format.escape(&spell.duration)),
+        ("ritual", flag(spell.ritual)),
+        ("concentration", flag(spell.concentration)),
+        ("classes", format.escape(&classes(spell))),
+        ("source", format.escape(&spell.source)),
+        ("description", format.description(&spell.description)),
+        ("higher_level", spell.higher_level.as_deref()
+            .map(|text| format.description(text))
+            .unwrap_or_default()),
+    ]
+}
+
+/// Renders a single spell through the spell template, without a document
+/// around it.
+pub fn spell(spell: &Spell, format: Format) -> String {
+    fill(&template(format, "spell"), &spell_fields(spell, format))
+}
+
+/// Renders `spells` in `format`. Markdown and HTML go through the spell
+/// template, wrapped in the document template, while CSV and JSON hold every
+/// column of the `spells` table. A single spell exported for a virtual
+/// tabletop is written on its own rather than in a list, since that's what
+/// their single item import takes.
+pub fn export(spells: &[Spell], title: &str, query: &Query, format: Format) -> String {
+    match format {
+        Format::Csv => return to_csv(spells),
+        Format::Json => return serde_json::to_string_pretty(&json!({
+            "title": title,
+            "query": query,
+            "count": spells.len(),
+            "spells": spells,
+        })).expect("Failed to write json"),
+        Format::Foundry | Format::Roll20 => {
+            let mut converted: Vec<Value> = spells.iter()
+                .map(|spell| {
+                    let description = format.description(&spell.description);
+                    let higher_level = spell.higher_level.as_deref()
+                        .map(|text| format.description(text))
+                        .unwrap_or_default();
+                    if format == Format::Foundry {
+                        vtt::foundry_item(spell, &description, &higher_level)
+                    } else {
+                        vtt::roll20_spell(spell, &description, &higher_level)
+                    }
+                })
+                .collect();
+            let value = if converted.len() == 1 {
+                converted.remove(0)
+            } else {
+                Value::Array(converted)
+            };
+            return serde_json::to_string_pretty(&value).expect("Failed to write json");
+        },
+        _ => {},
+    }
+
+    let rendered: Vec<String> = spells.iter()
+        .map(|spell| self::spell(spell, format))
+        .collect();
+
+    fill(&template(format, "document"), &[
+        ("title", format.escape(title)),
+        ("spells", rendered.join("\n")),
+    ])
+}
+
+fn to_csv(spells: &[Spell]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for spell in spells {
+        writer.serialize(spell).expect("Failed to write csv");
+    }
+    String::from_utf8(writer.into_inner().expect("Failed to write csv"))
+        .expect("Failed to write csv")
+}
+
+fn markdown(text: &str) -> String {
+    let mut out = String::new();
+    let mut previous_bullet = false;
+    for block in markup::blocks(text) {
+        let bullet = matches!(block, Block::Bullet(_));
+        if !out.is_empty() {
+            out.push_str(if bullet && previous_bullet { "\n" } else { "\n\n" });
+        }
+        previous_bullet = bullet;
+
+        match block {
+            Block::Heading(heading) => out.push_str(&format!("#### {}", heading)),
+            Block::Paragraph(paragraph) => out.push_str(paragraph),
+            Block::Bullet(item) => out.push_str(&format!("- {}", item)),
+            Block::Table(rows) => {
+                let lines: Vec<String> = rows.iter()
+                    .map(|row| format!("| {} |", row.join(" | ")))
+                    .collect();
+                out.push_str(&lines[0]);
+                out.push_str(&format!("\n|{}", " --- |".repeat(rows[0].len())));
+                for line in lines.iter().skip(1) {
+                    out.push('\n');
+                    out.push_str(line);
+                }
+            },
+        }
+    }
+    out
+}
+
+fn html_inline(text: &str) -> String {
+    markup::inline(text, Style::default())
+        .into_iter()
+        .map(|(run, style)| {
+            let run = Format::Html.escape(&run);
+            match (style.add_modifier.contains(Modifier::BOLD), style.add_modifier.contains(Modifier::ITALIC)) {
+                (true, true) => format!("<strong><em>{}</em></strong>", run),
+                (true, false) => format!("<strong>{}</strong>", run),
+                (false, true) => format!("<em>{}</em>", run),
+                (false, false) => run,
+            }
+        })
+        .collect()
+}
+
+fn html(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+    for block in markup::blocks(text) {
+        if !matches!(block, Block::Bullet(_)) && !list.is_empty() {
+            out.push(format!("<ul>{}</ul>", list.join("")));
+            list.clear();
+        }
+
+        match block {
+            Block::Heading(heading) => out.push(format!("<h4>{}</h4>", html_inline(heading))),
+            Block::Paragraph(paragraph) => out.push(format!("<p>{}</p>", html_inline(paragraph))),
+            Block::Bullet(item) => list.push(format!("<li>{}</li>", html_inline(item))),
+            Block::Table(rows) => {
+                let rows: Vec<String> = rows.iter()
+                    .enumerate()
+                    .map(|(i, row)| {
+                        let cell = if i == 0 { "th" } else { "td" };
+                        format!("<tr>{}</tr>", row.iter()
+                            .map(|text| format!("<{}>{}</{}>", cell, html_inline(text), cell))
+                            .collect::<String>())
+                    })
+                    .collect();
+                out.push(format!("<table>{}</table>", rows.join("")));
+            },
+        }
+    }
+    if !list.is_empty() {
+        out.push(format!("<ul>{}</ul>", list.join("")));
+    }
+    out.join("\n")
+}
+
+/// A file name for `title` in `format`, like "magic-missile.md".
+pub fn file_name(title: &str, format: Format) -> String {
+    let stem: String = title.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{}.{}", stem.trim_matches('-'), format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fill;
+
+    fn fields() -> Vec<(&'static str, String)> {
+        vec![
+            ("name", String::from("Fireball")),
+            ("level", String::from("3")),
+            ("higher_level", String::new()),
+        ]
+    }
+
+    #[test]
+    fn fills_plain_fields() {
+        assert_eq!(fill("# {{name}} ({{level}})", &fields()), "# Fireball (3)");
+        assert_eq!(fill("{{name}}{{name}}", &fields()), "FireballFireball");
+        assert_eq!(fill("no placeholders", &fields()), "no placeholders");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        assert_eq!(fill("{{name}} {{ritual}}", &fields()), "Fireball {{ritual}}");
+    }
+
+    #[test]
+    fn keeps_sections_with_a_value() {
+        assert_eq!(fill("a{{#name}} [{{name}}]{{/name}} b", &fields()), "a [Fireball] b");
+    }
+
+    #[test]
+    fn drops_empty_or_missing_sections() {
+        assert_eq!(fill("a{{#higher_level}} [{{higher_level}}]{{/higher_level}} b", &fields()), "a b");
+        assert_eq!(fill("a{{#ritual}} ritual{{/ritual}} b", &fields()), "a b");
+    }
+
+    #[test]
+    fn leaves_unclosed_sections() {
+        assert_eq!(fill("{{#name}} {{name}}", &fields()), "{{#name}} Fireball");
+        assert_eq!(fill("{{#ritual}}x{{/name}}", &fields()), "{{#ritual}}x{{/name}}");
+    }
+
+    #[test]
+    fn leaves_unterminated_placeholders() {
+        assert_eq!(fill("{{name}} {{level", &fields()), "Fireball {{level");
+        assert_eq!(fill("{{", &fields()), "{{");
+    }
+}
@@ -0,0 +1,113 @@
+//! Values picked from filter lists. Each knows how it's labelled in the
+//! list and what picking it does, so `StatefulList` can show any of them.
+
+use crate::app::Action;
+use dnd_tui::models::{Class, ItemCategory, Rarity, School};
+
+pub trait FilterValue: Clone {
+    fn label(&self) -> String;
+
+    /// What the app should do once this value is picked.
+    fn action(&self) -> Action;
+}
+
+/// Whether an item needs attunement, which items store as a flag.
+#[derive(Clone, Copy)]
+pub enum Attunement {
+    Required,
+    NotRequired,
+}
+
+impl Attunement {
+    pub const ALL: [Attunement; 2] = [Attunement::Required, Attunement::NotRequired];
+}
+
+/// "adventuring gear" as "Adventuring Gear", leaving "and" and "of" alone.
+fn title_case(name: String) -> String {
+    name.split(' ')
+        .map(|word| match word {
+            "and" | "of" => word.to_owned(),
+            _ => {
+                let mut chars = word.chars();
+                chars.next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            },
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl FilterValue for Class {
+    fn label(&self) -> String { title_case(String::from(*self)) }
+
+    fn action(&self) -> Action { Action::Class(*self) }
+}
+
+impl FilterValue for School {
+    fn label(&self) -> String { title_case(String::from(*self)) }
+
+    fn action(&self) -> Action { Action::School(*self) }
+}
+
+impl FilterValue for ItemCategory {
+    fn label(&self) -> String { title_case(String::from(*self)) }
+
+    fn action(&self) -> Action { Action::Category(*self) }
+}
+
+impl FilterValue for Rarity {
+    fn label(&self) -> String { title_case(String::from(*self)) }
+
+    fn action(&self) -> Action { Action::Rarity(*self) }
+}
+
+impl FilterValue for Attunement {
+    fn label(&self) -> String {
+        match self {
+            Attunement::Required => String::from("Required"),
+            Attunement::NotRequired => String::from("Not required"),
+        }
+    }
+
+    fn action(&self) -> Action {
+        Action::Attunement(matches!(self, Attunement::Required))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dnd_tui::models::{Class, ItemCategory, Rarity, School};
+    use crate::app::Action;
+
+    use super::{title_case, Attunement, FilterValue};
+
+    #[test]
+    fn title_cases_names() {
+        assert_eq!(title_case(String::from("adventuring gear")), "Adventuring Gear");
+        assert_eq!(title_case(String::from("mounts and vehicles")), "Mounts and Vehicles");
+        assert_eq!(title_case(String::from("staff of power")), "Staff of Power");
+        assert_eq!(title_case(String::from("and")), "and");
+        assert_eq!(title_case(String::new()), "");
+    }
+
+    #[test]
+    fn labels_values() {
+        assert_eq!(Class::Wizard.label(), "Wizard");
+        assert_eq!(School::Evocation.label(), "Evocation");
+        assert_eq!(ItemCategory::WondrousItems.label(), "Wondrous Items");
+        assert_eq!(Rarity::VeryRare.label(), "Very Rare");
+        assert_eq!(Attunement::Required.label(), "Required");
+        assert_eq!(Attunement::NotRequired.label(), "Not required");
+    }
+
+    #[test]
+    fn picking_values_filters_by_them() {
+        assert!(matches!(Class::Bard.action(), Action::Class(Class::Bard)));
+        assert!(matches!(School::Illusion.action(), Action::School(School::Illusion)));
+        assert!(matches!(ItemCategory::Ring.action(), Action::Category(ItemCategory::Ring)));
+        assert!(matches!(Rarity::Legendary.action(), Action::Rarity(Rarity::Legendary)));
+        assert!(matches!(Attunement::Required.action(), Action::Attunement(true)));
+        assert!(matches!(Attunement::NotRequired.action(), Action::Attunement(false)));
+    }
+}
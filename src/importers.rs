@@ -0,0 +1,405 @@
+//! Reads spells from the json layouts different sources publish them in.
+//! Every record is checked, so a bad file is reported instead of half
+//! imported.
+
+use std::convert::TryFrom;
+
+use serde_json::Value;
+
+use crate::models::{Class, School};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpellFormat {
+    /// The 5e-database SRD json, which the bundled data uses
+    Srd,
+    /// Spells from the Open5e API, either a list or a page of `results`
+    Open5e,
+    /// Flat records laid out like the JSON export, either a list or an
+    /// object with a `spells` list
+    Homebrew,
+}
+
+/// A spell read from any format, ready to be inserted.
+pub struct SpellRecord {
+    pub name: String,
+    pub description: String,
+    pub higher_level: Option<String>,
+    pub range: Option<String>,
+    pub verbal: bool,
+    pub somatic: bool,
+    pub material: bool,
+    pub material_text: Option<String>,
+    pub ritual: bool,
+    pub duration: String,
+    pub concentration: bool,
+    pub casting_time: String,
+    pub level: i32,
+    pub school: String,
+    /// Lowercase class names, each preceded by a comma like ",bard,wizard",
+    /// which is how `query_spell` matches them
+    pub classes: String,
+}
+
+pub(crate) fn field<'a>(record: &'a Value, key: &str) -> Result<&'a Value, String> {
+    match record.get(key) {
+        Some(Value::Null) | None => Err(format!("missing `{}`", key)),
+        Some(value) => Ok(value),
+    }
+}
+
+pub(crate) fn string(record: &Value, key: &str) -> Result<String, String> {
+    field(record, key)?
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| format!("`{}` should be a string", key))
+}
+
+fn optional_string(record: &Value, key: &str) -> Result<Option<String>, String> {
+    match record.get(key) {
+        Some(Value::Null) | None => Ok(None),
+        Some(Value::String(value)) if value.is_empty() => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(format!("`{}` should be a string", key)),
+    }
+}
+
+/// Booleans, which some sources write as "yes" and "no".
+fn boolean(record: &Value, key: &str) -> Result<bool, String> {
+    match record.get(key) {
+        Some(Value::Null) | None => Ok(false),
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(Value::String(value)) if value.eq_ignore_ascii_case("yes") => Ok(true),
+        Some(Value::String(value)) if value.eq_ignore_ascii_case("no") => Ok(false),
+        Some(_) => Err(format!("`{}` should be true or false", key)),
+    }
+}
+
+pub(crate) fn integer(record: &Value, key: &str) -> Result<i32, String> {
+    let value = field(record, key)?;
+    value.as_i64()
+        .or_else(|| value.as_str().and_then(|value| value.parse().ok()))
+        .map(|value| value as i32)
+        .ok_or_else(|| format!("`{}` should be a number", key))
+}
+
+/// Descriptions are stored one block per line. They come either as a list
+/// of blocks or as markdown with blank lines between paragraphs.
+fn text(value: &Value, key: &str) -> Result<String, String> {
+    let lines: Vec<&str> = match value {
+        Value::String(text) => text.lines().collect(),
+        Value::Array(lines) => lines.iter()
+            .map(|line| line.as_str().ok_or_else(|| format!("`{}` should hold strings", key)))
+            .collect::<Result<_, _>>()?,
+        _ => return Err(format!("`{}` should be a string or a list of strings", key)),
+    };
+    Ok(lines.into_iter()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n"))
+}
+
+fn optional_text(record: &Value, key: &str) -> Result<Option<String>, String> {
+    match record.get(key) {
+        Some(Value::Null) | None => Ok(None),
+        Some(value) => Ok(Some(text(value, key)?).filter(|text| !text.is_empty())),
+    }
+}
+
+fn school(name: &str) -> Result<String, String> {
+    School::try_from(name.trim().to_lowercase())
+        .map(Into::<String>::into)
+        .map_err(|_| format!("unknown school `{}`", name))
+}
+
+fn classes<'a>(names: impl Iterator<Item = &'a str>) -> Result<String, String> {
+    names.map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| Class::try_from(name.to_lowercase())
+            .map(|class| format!(",{}", Into::<String>::into(class)))
+            .map_err(|_| format!("unknown class `{}`", name)))
+        .collect()
+}
+
+/// Components written as "V, S, M" or as a list of letters.
+fn components(value: &Value) -> Result<(bool, bool, bool), String> {
+    let letters: Vec<String> = match value {
+        Value::String(components) => components.split(',')
+            .map(|component| component.trim().to_uppercase())
+            .collect(),
+        Value::Array(components) => components.iter()
+            .map(|component| component.as_str()
+                .map(|component| component.trim().to_uppercase())
+                .ok_or_else(|| String::from("`components` should hold strings")))
+            .collect::<Result<_, _>>()?,
+        _ => return Err(String::from("`components` should be a string or a list")),
+    };
+    let has = |letter: &str| letters.iter().any(|component| component.starts_with(letter));
+    Ok((has("V"), has("S"), has("M")))
+}
+
+fn srd(record: &Value) -> Result<SpellRecord, String> {
+    let (verbal, somatic, material) = components(field(record, "components")?)?;
+    let class_names = field(record, "classes")?
+        .as_array()
+        .ok_or("`classes` should be a list")?
+        .iter()
+        .map(|class| class["name"].as_str().ok_or("`classes` should hold named classes"))
+        .collect::<Result<Vec<&str>, _>>()?;
+
+    Ok(SpellRecord {
+        name: string(record, "name")?,
+        description: text(field(record, "desc")?, "desc")?,
+        higher_level: optional_text(record, "higher_level")?,
+        range: optional_string(record, "range")?,
+        verbal,
+        somatic,
+        material,
+        material_text: optional_string(record, "material")?,
+        ritual: boolean(record, "ritual")?,
+        duration: string(record, "duration")?,
+        concentration: boolean(record, "concentration")?,
+        casting_time: string(record, "casting_time")?,
+        level: integer(record, "level")?,
+        school: school(field(record, "school")?["name"].as_str().ok_or("`school` should be named")?)?,
+        classes: classes(class_names.into_iter())?,
+    })
+}
+
+fn open5e(record: &Value) -> Result<SpellRecord, String> {
+    let (verbal, somatic, material) = components(field(record, "components")?)?;
+    let level = if record.get("level_int").is_some() {
+        integer(record, "level_int")?
+    } else {
+        integer(record, "spell_level")?
+    };
+
+    Ok(SpellRecord {
+        name: string(record, "name")?,
+        description: text(field(record, "desc")?, "desc")?,
+        higher_level: optional_text(record, "higher_level")?,
+        range: optional_string(record, "range")?,
+        verbal,
+        somatic,
+        material,
+        material_text: optional_string(record, "material")?,
+        ritual: boolean(record, "ritual")?,
+        duration: string(record, "duration")?,
+        concentration: boolean(record, "concentration")?,
+        casting_time: string(record, "casting_time")?,
+        level,
+        school: school(&string(record, "school")?)?,
+        // Spells outside the SRD class lists name subclasses or other
+        // books here, which can't be filtered on
+        classes: classes(string(record, "dnd_class")?
+            .split(',')
+            .filter(|name| Class::try_from(name.trim().to_lowercase()).is_ok()))?,
+    })
+}
+
+fn homebrew(record: &Value) -> Result<SpellRecord, String> {
+    let (verbal, somatic, material) = match record.get("components") {
+        Some(components) if !components.is_null() => self::components(components)?,
+        _ => (
+            boolean(record, "verbal")?,
+            boolean(record, "somatic")?,
+            boolean(record, "material")?,
+        ),
+    };
+    let classes = match record.get("classes") {
+        Some(Value::String(names)) => self::classes(names.split(','))?,
+        Some(Value::Array(names)) => self::classes(names.iter()
+            .map(|name| name.as_str().ok_or("`classes` should hold strings"))
+            .collect::<Result<Vec<&str>, _>>()?
+            .into_iter())?,
+        Some(Value::Null) | None => String::new(),
+        Some(_) => return Err(String::from("`classes` should be a string or a list")),
+    };
+
+    Ok(SpellRecord {
+        name: string(record, "name")?,
+        description: text(field(record, "description")?, "description")?,
+        higher_level: optional_text(record, "higher_level")?,
+        range: optional_string(record, "range")?,
+        verbal,
+        somatic,
+        material,
+        material_text: optional_string(record, "material_text")?,
+        ritual: boolean(record, "ritual")?,
+        duration: string(record, "duration")?,
+        concentration: boolean(record, "concentration")?,
+        casting_time: string(record, "casting_time")?,
+        level: integer(record, "level")?,
+        school: school(&string(record, "school")?)?,
+        classes,
+    })
+}
+
+/// Reads every record, or describes each one that couldn't be read.
+pub fn parse_spells(records: &[Value], format: SpellFormat) -> Result<Vec<SpellRecord>, Vec<String>> {
+    let parse = match format {
+        SpellFormat::Srd => srd,
+        SpellFormat::Open5e => open5e,
+        SpellFormat::Homebrew => homebrew,
+    };
+
+    let mut spells = Vec::new();
+    let mut errors = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        match parse(record) {
+            Ok(spell) => spells.push(spell),
+            Err(error) => errors.push(format!(
+                "record {} ({}): {}",
+                i + 1,
+                record["name"].as_str().unwrap_or("unnamed"),
+                error
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(spells)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{parse_spells, SpellFormat, SpellRecord};
+
+    fn parse_one(record: Value, format: SpellFormat) -> Result<SpellRecord, Vec<String>> {
+        parse_spells(&[record], format).map(|mut spells| spells.remove(0))
+    }
+
+    fn open5e_record(fields: Value) -> Value {
+        let mut record = json!({
+            "name": "Fire Bolt",
+            "desc": "You hurl a mote of fire.",
+            "components": "V, S",
+            "duration": "Instantaneous",
+            "casting_time": "1 action",
+            "level_int": 0,
+            "school": "Evocation",
+            "dnd_class": "Sorcerer, Wizard",
+        });
+        record.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        record
+    }
+
+    fn homebrew_record(fields: Value) -> Value {
+        let mut record = json!({
+            "name": "Zap",
+            "description": "Zaps.",
+            "duration": "Instantaneous",
+            "casting_time": "1 action",
+            "level": 1,
+            "school": "evocation",
+        });
+        record.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        record
+    }
+
+    fn open5e(fields: Value) -> Result<SpellRecord, Vec<String>> {
+        parse_one(open5e_record(fields), SpellFormat::Open5e)
+    }
+
+    fn homebrew(fields: Value) -> Result<SpellRecord, Vec<String>> {
+        parse_one(homebrew_record(fields), SpellFormat::Homebrew)
+    }
+
+    #[test]
+    fn parses_srd_records() {
+        let spell = parse_one(json!({
+            "name": "Fireball",
+            "desc": ["A bright streak.", "", "Each creature takes 8d6."],
+            "higher_level": ["1d6 more per slot level."],
+            "range": "150 feet",
+            "components": ["V", "S", "M"],
+            "material": "A tiny ball of bat guano.",
+            "ritual": false,
+            "duration": "Instantaneous",
+            "concentration": false,
+            "casting_time": "1 action",
+            "level": 3,
+            "school": {"name": "Evocation"},
+            "classes": [{"name": "Sorcerer"}, {"name": "Wizard"}],
+        }), SpellFormat::Srd).unwrap();
+
+        assert_eq!(spell.description, "A bright streak.\nEach creature takes 8d6.");
+        assert_eq!(spell.higher_level.as_deref(), Some("1d6 more per slot level."));
+        assert_eq!((spell.verbal, spell.somatic, spell.material), (true, true, true));
+        assert_eq!((spell.level, spell.school.as_str()), (3, "evocation"));
+        assert_eq!(spell.classes, ",sorcerer,wizard");
+    }
+
+    #[test]
+    fn reads_yes_and_no_as_booleans() {
+        let spell = open5e(json!({"ritual": "yes", "concentration": "no"})).unwrap();
+        assert!(spell.ritual);
+        assert!(!spell.concentration);
+
+        let spell = homebrew(json!({"ritual": true, "concentration": "Yes"})).unwrap();
+        assert!(spell.ritual && spell.concentration);
+
+        let errors = open5e(json!({"ritual": "maybe"})).err().unwrap();
+        assert_eq!(errors, ["record 1 (Fire Bolt): `ritual` should be true or false"]);
+    }
+
+    #[test]
+    fn reads_components_as_a_string_or_a_list() {
+        let spell = open5e(json!({"components": "V, M"})).unwrap();
+        assert_eq!((spell.verbal, spell.somatic, spell.material), (true, false, true));
+
+        let spell = homebrew(json!({"components": ["s", "m"]})).unwrap();
+        assert_eq!((spell.verbal, spell.somatic, spell.material), (false, true, true));
+
+        // Without components, homebrew records may list each one as a flag
+        let spell = homebrew(json!({"verbal": true})).unwrap();
+        assert_eq!((spell.verbal, spell.somatic, spell.material), (true, false, false));
+
+        let errors = open5e(json!({"components": 3})).err().unwrap();
+        assert_eq!(errors, ["record 1 (Fire Bolt): `components` should be a string or a list"]);
+    }
+
+    #[test]
+    fn reads_the_open5e_level_from_level_int_or_spell_level() {
+        let spell = open5e(json!({"level_int": 2})).unwrap();
+        assert_eq!(spell.level, 2);
+
+        let mut record = open5e_record(json!({"spell_level": "4"}));
+        record.as_object_mut().unwrap().remove("level_int");
+        assert_eq!(parse_one(record, SpellFormat::Open5e).unwrap().level, 4);
+    }
+
+    #[test]
+    fn keeps_only_known_open5e_classes() {
+        let spell = open5e(json!({"dnd_class": "Wizard, Ritual Caster"})).unwrap();
+        assert_eq!(spell.classes, ",wizard");
+    }
+
+    #[test]
+    fn rejects_unknown_schools_and_classes() {
+        let errors = homebrew(json!({"school": "chronurgy"})).err().unwrap();
+        assert_eq!(errors, ["record 1 (Zap): unknown school `chronurgy`"]);
+
+        let errors = homebrew(json!({"classes": "wizard, artificer"})).err().unwrap();
+        assert_eq!(errors, ["record 1 (Zap): unknown class `artificer`"]);
+    }
+
+    #[test]
+    fn describes_every_invalid_record() {
+        let records = [
+            homebrew_record(json!({"level": "high"})),
+            homebrew_record(json!({"name": "Fine"})),
+            json!({"description": "No name."}),
+        ];
+        let errors = parse_spells(&records, SpellFormat::Homebrew).err().unwrap();
+        assert_eq!(errors, [
+            "record 1 (Zap): `level` should be a number",
+            "record 3 (unnamed): missing `name`",
+        ]);
+    }
+}
@@ -0,0 +1,335 @@
+//! Pane arrangements for the views, read from `layouts/default.toml` or a
+//! file of the same shape set as `layout` in the config.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use tui::layout::{Constraint, Direction};
+
+/// A pane: either a widget, or a container splitting its area between
+/// `panes`.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Node {
+    #[serde(default)]
+    pub size: Size,
+    pub widget: Option<Widget>,
+    pub direction: Option<Split>,
+    #[serde(default)]
+    pub panes: Vec<Node>,
+}
+
+/// A pane's share of its container.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Size(pub Constraint);
+
+impl Default for Size {
+    fn default() -> Size {
+        Size(Constraint::Min(0))
+    }
+}
+
+impl TryFrom<String> for Size {
+    type Error = String;
+
+    fn try_from(size: String) -> Result<Self, Self::Error> {
+        let constraint = if size == "fill" {
+            Constraint::Min(0)
+        } else if let Some(percent) = size.strip_suffix('%') {
+            match percent.parse() {
+                Ok(percent) if percent <= 100 => Constraint::Percentage(percent),
+                _ => return Err(format!("`{}` isn't a percentage from 0% to 100%", size)),
+            }
+        } else {
+            match size.parse() {
+                Ok(length) => Constraint::Length(length),
+                Err(_) => return Err(format!(
+                    "`{}` should be a number of lines, a percentage or \"fill\"",
+                    size
+                )),
+            }
+        };
+        Ok(Size(constraint))
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Split {
+    Horizontal,
+    Vertical,
+}
+
+impl From<Split> for Direction {
+    fn from(split: Split) -> Direction {
+        match split {
+            Split::Horizontal => Direction::Horizontal,
+            Split::Vertical => Direction::Vertical,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Widget {
+    Search,
+    Class,
+    School,
+    Level,
+    CharacterLevel,
+    SpellResults,
+    Category,
+    Rarity,
+    Attunement,
+    ItemResults,
+    Progression,
+}
+
+/// The widgets each view can show, since each view's results are drawn from
+/// its own data.
+const SPELL_WIDGETS: [Widget; 6] = [
+    Widget::Search,
+    Widget::Class,
+    Widget::School,
+    Widget::Level,
+    Widget::CharacterLevel,
+    Widget::SpellResults,
+];
+const ITEM_WIDGETS: [Widget; 4] = [
+    Widget::Category,
+    Widget::Rarity,
+    Widget::Attunement,
+    Widget::ItemResults,
+];
+const CLASS_WIDGETS: [Widget; 2] = [Widget::Class, Widget::Progression];
+
+/// A layout file. Views it leaves out keep the default layout.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    spells: Option<Node>,
+    items: Option<Node>,
+    classes: Option<Node>,
+}
+
+#[derive(Clone)]
+pub struct Layouts {
+    pub spells: Node,
+    pub items: Node,
+    pub classes: Node,
+}
+
+impl Default for Layouts {
+    fn default() -> Layouts {
+        let file: File = toml::from_str(include_str!("../layouts/default.toml"))
+            .expect("Invalid default layout");
+        Layouts {
+            spells: file.spells.expect("No spells layout"),
+            items: file.items.expect("No items layout"),
+            classes: file.classes.expect("No classes layout"),
+        }
+    }
+}
+
+impl Layouts {
+    /// Reads the layout file at `path`, returning every problem with it.
+    pub fn load(path: &Path) -> Result<Layouts, Vec<String>> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| vec![format!("Couldn't read {}: {}", path.display(), error)])?;
+        let file: File = toml::from_str(&text)
+            .map_err(|error| vec![format!("{}: {}", path.display(), error.to_string().trim_end())])?;
+
+        let default = Layouts::default();
+        let layouts = Layouts {
+            spells: file.spells.unwrap_or(default.spells),
+            items: file.items.unwrap_or(default.items),
+            classes: file.classes.unwrap_or(default.classes),
+        };
+        let mut errors = Vec::new();
+        layouts.spells.check_view("spells", &SPELL_WIDGETS, Widget::SpellResults, &mut errors);
+        layouts.items.check_view("items", &ITEM_WIDGETS, Widget::ItemResults, &mut errors);
+        layouts.classes.check_view("classes", &CLASS_WIDGETS, Widget::Progression, &mut errors);
+        if errors.is_empty() {
+            Ok(layouts)
+        } else {
+            Err(errors.into_iter().map(|error| format!("{}: {}", path.display(), error)).collect())
+        }
+    }
+}
+
+impl Node {
+    /// Collects what's wrong with the view at `name`, which also has to show
+    /// its `results` exactly once, as the other panes filter what's in them.
+    fn check_view(&self, name: &str, widgets: &[Widget], results: Widget,
+                  errors: &mut Vec<String>) {
+        self.check(name, widgets, errors);
+        match self.count(results) {
+            1 => {},
+            0 => errors.push(format!("{} has no pane for its results", name)),
+            count => errors.push(format!(
+                "{} shows its results in {} panes instead of one",
+                name,
+                count
+            )),
+        }
+    }
+
+    /// How many panes in this one, itself included, show `widget`.
+    fn count(&self, widget: Widget) -> usize {
+        let own = usize::from(self.widget == Some(widget));
+        own + self.panes.iter().map(|pane| pane.count(widget)).sum::<usize>()
+    }
+
+    /// Collects what's wrong with the pane at `name` and the panes in it.
+    /// The top of a view has to be a container, since that's what the app
+    /// keeps for each view.
+    fn check(&self, name: &str, widgets: &[Widget], errors: &mut Vec<String>) {
+        match (self.widget, self.direction, self.panes.is_empty()) {
+            (Some(_), None, true) if !name.contains('.') => {
+                errors.push(format!("{} has to be a container with a direction and panes", name));
+            },
+            (Some(widget), None, true) => {
+                if !widgets.contains(&widget) {
+                    errors.push(format!("{}: this widget can't be used in the {} view", name, view(name)));
+                }
+            },
+            (None, Some(_), false) => {
+                for (i, pane) in self.panes.iter().enumerate() {
+                    pane.check(&format!("{}.panes[{}]", name, i), widgets, errors);
+                }
+            },
+            (Some(_), _, _) => {
+                errors.push(format!("{} has a widget, so it can't have a direction or panes", name));
+            },
+            (None, None, _) => errors.push(format!("{} needs a widget or a direction", name)),
+            (None, Some(_), true) => errors.push(format!("{} has a direction but no panes", name)),
+        }
+    }
+}
+
+/// The view a pane's name starts with.
+fn view(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Layouts;
+
+    /// The problems `load` finds with a layout file holding `text`.
+    fn errors(name: &str, text: &str) -> Vec<String> {
+        let path = std::env::temp_dir()
+            .join(format!("dnd_tui-layout-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let loaded = Layouts::load(&path);
+        fs::remove_file(&path).unwrap();
+        let prefix = format!("{}: ", path.display());
+        loaded.err().unwrap_or_default()
+            .into_iter()
+            .map(|error| error.strip_prefix(&prefix).map(String::from).unwrap_or(error))
+            .collect()
+    }
+
+    /// A classes view with `panes` next to each other.
+    fn classes(panes: &str) -> String {
+        format!("[classes]\ndirection = \"horizontal\"\n{}", panes)
+    }
+
+    #[test]
+    fn loads_the_default_layout() {
+        let layouts = Layouts::default();
+        assert_eq!(layouts.spells.panes.len(), 2);
+        assert_eq!(layouts.items.panes.len(), 2);
+        assert_eq!(layouts.classes.panes.len(), 2);
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("layouts/default.toml");
+        assert!(Layouts::load(&path).is_ok());
+        assert!(errors("empty", "").is_empty());
+    }
+
+    #[test]
+    fn reports_unreadable_files() {
+        let missing = std::env::temp_dir().join("dnd_tui-layout-missing.toml");
+        let errors = Layouts::load(&missing).err().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(&format!("Couldn't read {}: ", missing.display())));
+    }
+
+    #[test]
+    fn reports_invalid_sizes() {
+        let percent = errors("percent", &classes(r#"
+            [[classes.panes]]
+            size = "120%"
+            widget = "progression"
+        "#));
+        assert_eq!(percent.len(), 1);
+        assert!(percent[0].contains("`120%` isn't a percentage from 0% to 100%"), "{}", percent[0]);
+
+        let lines = errors("lines", &classes(r#"
+            [[classes.panes]]
+            size = "wide"
+            widget = "progression"
+        "#));
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("`wide` should be a number of lines, a percentage or \"fill\""));
+    }
+
+    #[test]
+    fn reports_misplaced_widgets() {
+        assert_eq!(errors("top", "[classes]\nwidget = \"progression\"\n"), [
+            "classes has to be a container with a direction and panes",
+        ].map(String::from));
+        assert_eq!(errors("view", &classes(r#"
+            [[classes.panes]]
+            widget = "progression"
+            [[classes.panes]]
+            widget = "rarity"
+        "#)), [
+            "classes.panes[1]: this widget can't be used in the classes view",
+        ].map(String::from));
+    }
+
+    #[test]
+    fn reports_panes_that_are_neither_widgets_nor_containers() {
+        assert_eq!(errors("both", &classes(r#"
+            [[classes.panes]]
+            widget = "progression"
+            direction = "vertical"
+        "#)), [
+            "classes.panes[0] has a widget, so it can't have a direction or panes",
+        ].map(String::from));
+        assert_eq!(errors("neither", &classes(r#"
+            [[classes.panes]]
+            widget = "progression"
+            [[classes.panes]]
+            size = "3"
+        "#)), ["classes.panes[1] needs a widget or a direction"].map(String::from));
+        assert_eq!(errors("no-panes", &classes(r#"
+            [[classes.panes]]
+            widget = "progression"
+            [[classes.panes]]
+            direction = "vertical"
+        "#)), ["classes.panes[1] has a direction but no panes"].map(String::from));
+    }
+
+    #[test]
+    fn requires_one_results_pane() {
+        assert_eq!(errors("missing", &classes(r#"
+            [[classes.panes]]
+            widget = "class"
+        "#)), ["classes has no pane for its results"].map(String::from));
+        assert_eq!(errors("twice", &classes(r#"
+            [[classes.panes]]
+            widget = "progression"
+            [[classes.panes]]
+            direction = "vertical"
+            [[classes.panes.panes]]
+            widget = "progression"
+        "#)), ["classes shows its results in 2 panes instead of one"].map(String::from));
+    }
+}
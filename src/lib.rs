@@ -0,0 +1,41 @@
+//! The spell database behind dnd_tui, for other tools that want to read or
+//! import the same data.
+//!
+//! The database is SQLite. [`db_utils::connect`] opens one and brings its
+//! schema up to date. An empty database can be filled from the bundled SRD
+//! with [`db_utils::build_db`], which also imports any other files listed in
+//! its [`db_utils::ImportPaths`].
+//!
+//! ```no_run
+//! use dnd_tui::db_utils::{build_db, connect, is_empty, query_spell, ImportPaths, Query};
+//! use dnd_tui::models::Class;
+//!
+//! let conn = connect("5e.db").unwrap();
+//! if is_empty(&conn) {
+//!     build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+//! }
+//! let spells = query_spell(&Query {
+//!     class: Some(Class::Wizard),
+//!     level: Some(3),
+//!     ..Query::default()
+//! }, &conn);
+//! for spell in spells {
+//!     println!("{}", spell.name);
+//! }
+//! ```
+//!
+//! Spells from other files are read with [`db_utils::import_file`], which
+//! understands the layouts in [`importers::SpellFormat`].
+
+// Diesel 1.4's derives implement their traits inside generated functions
+#![allow(non_local_definitions)]
+
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
+
+pub mod db_utils;
+pub mod importers;
+pub mod models;
+mod schema;
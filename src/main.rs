@@ -1,30 +1,59 @@
-#[macro_use]
-extern crate diesel;
-extern crate dotenv;
-
-use std::{error::Error, io, time::Duration};
-use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
+use std::{error::Error, io::{self, Write}};
+use termion::{input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
 use tui::{backend::TermionBackend, Terminal};
 
 mod events;
-mod db_utils;
-mod models;
-mod schema;
 mod app;
 mod components;
 mod components_ui;
+mod markup;
+mod export;
+mod vtt;
+mod clipboard;
+mod cli;
+mod ansi;
+mod server;
+mod config;
+mod filters;
+mod layout;
+
+use events::Events;
+use app::App;
+use clap::Parser;
+use cli::{Cli, Command, ConfigCommand};
+use config::Config;
+
+fn main() {
+    if let Err(error) = run(Cli::parse()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
-use events::{Config, Event, Events};
-use app::{App, Component};
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(&cli);
+    // `config check` lists what's wrong with the config instead of stopping
+    if let Some(Command::Config(ConfigCommand::Check)) = cli.command {
+        return cli::check_config(config);
+    }
+    config::init(config.map_err(|errors| errors.join("\n"))?);
 
-fn main() -> Result<(), Box<dyn Error>> {
-    use log::LevelFilter;
+    match cli.command {
+        Some(Command::Search(args)) => cli::search(args),
+        Some(Command::Show(args)) => cli::show(args),
+        Some(Command::Import(args)) => cli::import(args),
+        Some(Command::Serve(args)) => cli::serve(args),
+        Some(Command::Campaign(command)) => cli::campaign(command),
+        Some(Command::Config(_)) => Ok(()),
+        None => run_tui(),
+    }
+}
 
-    simple_logging::log_to_file("dnd-tui.log", LevelFilter::Info);
-    let events = Events::with_config(Config {
-        tick_rate: Duration::from_millis(200),
-        ..Config::default()
-    });
+fn run_tui() -> Result<(), Box<dyn Error>> {
+    let config = config::get();
+    simple_logging::log_to_file(&config.log_file, config.log_level).ok();
+    let conn = dnd_tui::db_utils::connect(&config.database)?;
+    let events = Events::new(config.tick_rate);
 
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
@@ -33,33 +62,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
 
-    let mut app = App::new("DND tui");
+    let mut app = App::new("DND tui", conn, events.sender());
     loop {
-        terminal.draw(|f| app.component_tree.draw(f, f.size(), &app.search_results))?;
+        terminal.draw(|f| app.draw(f))?;
 
-        match events.next()? {
-            Event::Input(key) => match key {
-                Key::Char(c) => {
-                    app.on_key(c);
-                }
-                Key::Up => {
-                    app.on_up();
-                }
-                Key::Down => {
-                    app.on_down();
-                }
-                Key::Left => {
-                    app.on_left();
-                }
-                Key::Right => {
-                    app.on_right();
-                }
-                Key::Esc => {
-                    app.on_unselect();
-                }
-                _ => {}
-            },
-            _ => {}
+        app.on_event(events.next()?);
+        if let Some(text) = app.clipboard.take() {
+            let backend = terminal.backend_mut();
+            write!(backend, "{}", clipboard::osc52(&text))?;
+            backend.flush()?;
         }
         if app.should_quit {
             break;
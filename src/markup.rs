@@ -0,0 +1,298 @@
+use crate::components::glossary_matches;
+use crate::config::theme;
+use dnd_tui::db_utils::GlossaryEntry;
+
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+
+/// The block level structure of a description. Descriptions are stored one
+/// block per line, the way the SRD json lists them.
+pub enum Block<'a> {
+    Heading(&'a str),
+    Paragraph(&'a str),
+    Bullet(&'a str),
+    Table(Vec<Vec<&'a str>>),
+}
+
+fn table_cells(line: &str) -> Vec<&str> {
+    line.trim()
+        .trim_matches('|')
+        .split('|')
+        .map(|cell| cell.trim())
+        .collect()
+}
+
+fn is_separator(line: &str) -> bool {
+    line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+pub fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        if line.starts_with('|') {
+            if is_separator(line) {
+                continue;
+            }
+            match blocks.last_mut() {
+                Some(Block::Table(rows)) => rows.push(table_cells(line)),
+                _ => blocks.push(Block::Table(vec![table_cells(line)])),
+            }
+        } else if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+            blocks.push(Block::Bullet(item));
+        } else if line.starts_with('#') {
+            blocks.push(Block::Heading(line.trim_start_matches('#').trim()));
+        } else {
+            blocks.push(Block::Paragraph(line));
+        }
+    }
+    blocks
+}
+
+/// Splits `*italic*`, `**bold**` and `***bold italic***` (or the `_`
+/// equivalents) markup into runs of text and the style they are drawn with.
+pub fn inline(text: &str, base: Style) -> Vec<(String, Style)> {
+    let mut runs = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = match rest.find(['*', '_']) {
+            Some(start) => start,
+            None => break,
+        };
+        let marker = rest.as_bytes()[start] as char;
+        let width = rest[start..].chars().take_while(|c| *c == marker).count().min(3);
+        let delimiter = &rest[start..start + width];
+        let body = &rest[start + width..];
+
+        // Underscores inside words, like snake_case, aren't markup
+        let intraword = marker == '_' && start > 0
+            && rest[..start].chars().last().is_some_and(char::is_alphanumeric);
+        match body.find(delimiter) {
+            Some(end) if end > 0 && !intraword => {
+                if start > 0 {
+                    runs.push((rest[..start].to_owned(), base));
+                }
+                let style = match width {
+                    1 => base.add_modifier(Modifier::ITALIC),
+                    2 => base.add_modifier(Modifier::BOLD),
+                    _ => base.add_modifier(Modifier::BOLD | Modifier::ITALIC),
+                };
+                runs.push((body[..end].to_owned(), style));
+                rest = &body[end + width..];
+            },
+            _ => {
+                runs.push((rest[..start + width].to_owned(), base));
+                rest = body;
+            }
+        }
+    }
+    if !rest.is_empty() {
+        runs.push((rest.to_owned(), base));
+    }
+    runs
+}
+
+/// Splits `text` into spans drawn with `style`, with every known glossary
+/// term highlighted.
+pub fn highlight_glossary(text: &str, style: Style, glossary: &[GlossaryEntry]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last_end = 0;
+    for (start, end, _) in glossary_matches(text, glossary) {
+        if start > last_end {
+            spans.push(Span::styled(text[last_end..start].to_owned(), style));
+        }
+        spans.push(Span::styled(
+            text[start..end].to_owned(),
+            style.fg(theme().link).add_modifier(Modifier::UNDERLINED)
+        ));
+        last_end = end;
+    }
+    if last_end < text.len() {
+        spans.push(Span::styled(text[last_end..].to_owned(), style));
+    }
+    spans
+}
+
+fn line(text: &str, base: Style, glossary: &[GlossaryEntry]) -> Vec<Span<'static>> {
+    inline(text, base)
+        .into_iter()
+        .flat_map(|(run, style)| highlight_glossary(&run, style, glossary))
+        .collect()
+}
+
+fn plain_width(text: &str) -> usize {
+    inline(text, Style::default())
+        .iter()
+        .map(|(run, _)| run.chars().count())
+        .sum()
+}
+
+fn table(rows: &[Vec<&str>], glossary: &[GlossaryEntry]) -> Vec<Spans<'static>> {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| rows.iter()
+            .filter_map(|row| row.get(column))
+            .map(|cell| plain_width(cell))
+            .max()
+            .unwrap_or(0))
+        .collect();
+
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let base = if i == 0 {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let mut spans = Vec::new();
+            for (column, width) in widths.iter().enumerate() {
+                let cell = row.get(column).copied().unwrap_or("");
+                spans.extend(line(cell, base, glossary));
+                spans.push(Span::raw(" ".repeat(width - plain_width(cell) + 2)));
+            }
+            Spans::from(spans)
+        })
+        .collect()
+}
+
+/// Renders a stored description into lines for a `Paragraph`, leaving a
+/// blank line between blocks but keeping lists and tables together.
+pub fn render(text: &str, glossary: &[GlossaryEntry]) -> Vec<Spans<'static>> {
+    let mut lines = Vec::new();
+    let mut previous_bullet = false;
+    for block in blocks(text) {
+        let bullet = matches!(block, Block::Bullet(_));
+        if !lines.is_empty() && (!bullet || !previous_bullet) {
+            lines.push(Spans::default());
+        }
+        previous_bullet = bullet;
+
+        match block {
+            Block::Heading(heading) => lines.push(Spans::from(
+                line(heading, Style::default().add_modifier(Modifier::BOLD), glossary)
+            )),
+            Block::Paragraph(paragraph) => lines.push(Spans::from(
+                line(paragraph, Style::default(), glossary)
+            )),
+            Block::Bullet(item) => lines.push(Spans::from(
+                vec![Span::raw("  • ")].into_iter()
+                    .chain(line(item, Style::default(), glossary))
+                    .collect::<Vec<Span>>()
+            )),
+            Block::Table(rows) => lines.extend(table(&rows, glossary)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::style::{Modifier, Style};
+    use tui::text::Spans;
+
+    use dnd_tui::db_utils::GlossaryEntry;
+    use crate::config::{self, theme};
+
+    use super::{blocks, highlight_glossary, inline, render, Block};
+
+    fn text(lines: &[Spans]) -> Vec<String> {
+        lines.iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect()
+    }
+
+    fn frightened() -> Vec<GlossaryEntry> {
+        vec![GlossaryEntry {
+            id: 1,
+            term: String::from("Frightened"),
+            kind: String::from("condition"),
+            definition: String::from("Can't move closer to the source of its fear."),
+        }]
+    }
+
+    #[test]
+    fn splits_descriptions_into_blocks() {
+        let blocks = blocks(
+            "## Fireball\n\n  A bright streak.  \n- one\n* two\n| a | b |\n|---|:-:|\n| 1 | 2 |"
+        );
+        assert_eq!(blocks.len(), 5);
+        assert!(matches!(blocks[0], Block::Heading("Fireball")));
+        assert!(matches!(blocks[1], Block::Paragraph("A bright streak.")));
+        assert!(matches!(blocks[2], Block::Bullet("one")));
+        assert!(matches!(blocks[3], Block::Bullet("two")));
+        match &blocks[4] {
+            Block::Table(rows) => assert_eq!(rows, &[vec!["a", "b"], vec!["1", "2"]]),
+            _ => panic!("expected a table"),
+        }
+    }
+
+    #[test]
+    fn styles_bold_and_italic_runs() {
+        let base = Style::default();
+        assert_eq!(inline("a **bold** and *it* and ***both***", base), [
+            (String::from("a "), base),
+            (String::from("bold"), base.add_modifier(Modifier::BOLD)),
+            (String::from(" and "), base),
+            (String::from("it"), base.add_modifier(Modifier::ITALIC)),
+            (String::from(" and "), base),
+            (String::from("both"), base.add_modifier(Modifier::BOLD | Modifier::ITALIC)),
+        ]);
+        assert_eq!(inline("_under_", base), [
+            (String::from("under"), base.add_modifier(Modifier::ITALIC)),
+        ]);
+    }
+
+    #[test]
+    fn leaves_stray_markers_alone() {
+        let base = Style::default();
+        for plain in ["snake_case_name", "5 * 3", "**unclosed"] {
+            let runs = inline(plain, base);
+            assert!(runs.iter().all(|(_, style)| *style == base), "{}", plain);
+            assert_eq!(runs.into_iter().map(|(run, _)| run).collect::<String>(), plain);
+        }
+    }
+
+    #[test]
+    fn renders_blocks_apart_but_keeps_lists_and_tables_together() {
+        let description = "# Fireball\nA bright streak.\n- one\n- two\n\
+                           | Level | Damage |\n|---|---|\n| 3 | 8d6 |";
+        let lines = render(description, &[]);
+        assert_eq!(text(&lines), [
+            "Fireball",
+            "",
+            "A bright streak.",
+            "",
+            "  • one",
+            "  • two",
+            "",
+            "Level  Damage  ",
+            "3      8d6     ",
+        ]);
+        assert!(lines[0].0[0].style.add_modifier.contains(Modifier::BOLD));
+        assert!(lines[7].0[0].style.add_modifier.contains(Modifier::BOLD));
+        assert!(!lines[8].0[0].style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn pads_table_cells_by_their_unstyled_width() {
+        let lines = render("| **Level** | Damage |\n| 3 | 8d6 |", &[]);
+        assert_eq!(text(&lines), ["Level  Damage  ", "3      8d6     "]);
+    }
+
+    #[test]
+    fn highlights_whole_glossary_terms() {
+        config::init_default();
+        let style = Style::default();
+        let link = style.fg(theme().link).add_modifier(Modifier::UNDERLINED);
+
+        let spans = highlight_glossary("You are frightened.", style, &frightened());
+        let runs: Vec<(&str, Style)> = spans.iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        assert_eq!(runs, [("You are ", style), ("frightened", link), (".", style)]);
+
+        let spans = highlight_glossary("Unfrightened", style, &frightened());
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].style, style);
+    }
+}
@@ -1,4 +1,5 @@
 use std::convert::TryFrom;
+use serde::Serialize;
 
 pub enum Damage {
     Acid = 0,
@@ -39,7 +40,8 @@ impl TryFrom<String> for Damage {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize)]
+#[serde(into = "String")]
 pub enum School {
     Abjuration = 0,
     Conjuration = 1,
@@ -51,6 +53,20 @@ pub enum School {
     Transmutation = 7,
 }
 
+impl School {
+    /// Every school, in list order.
+    pub const ALL: [School; 8] = [
+        School::Abjuration,
+        School::Conjuration,
+        School::Divination,
+        School::Enchantment,
+        School::Evocation,
+        School::Illusion,
+        School::Necromancy,
+        School::Transmutation,
+    ];
+}
+
 impl TryFrom<String> for School {
     type Error = &'static str;
 
@@ -69,14 +85,14 @@ impl TryFrom<String> for School {
     }
 }
 
-impl Into<String> for School {
-    fn into(self) -> String {
-        match self {
+impl From<School> for String {
+    fn from(school: School) -> String {
+        match school {
             School::Abjuration => String::from("abjuration"),
             School::Conjuration => String::from("conjuration"),
             School::Divination => String::from("divination"),
             School::Enchantment => String::from("enchantment"),
-            School::Evocation => String::from("evocatoin"),
+            School::Evocation => String::from("evocation"),
             School::Illusion => String::from("illusion"),
             School::Necromancy => String::from("necromancy"),
             School::Transmutation => String::from("transmutation"),
@@ -84,7 +100,8 @@ impl Into<String> for School {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize)]
+#[serde(into = "String")]
 pub enum Class {
     Barbarian = 0,
     Bard = 1,
@@ -100,6 +117,24 @@ pub enum Class {
     Wizard = 11,
 }
 
+impl Class {
+    /// Every class, in list order.
+    pub const ALL: [Class; 12] = [
+        Class::Barbarian,
+        Class::Bard,
+        Class::Cleric,
+        Class::Druid,
+        Class::Fighter,
+        Class::Monk,
+        Class::Paladin,
+        Class::Ranger,
+        Class::Rogue,
+        Class::Sorcerer,
+        Class::Warlock,
+        Class::Wizard,
+    ];
+}
+
 impl TryFrom<String> for Class {
     type Error = String;
 
@@ -122,9 +157,9 @@ impl TryFrom<String> for Class {
     }
 }
 
-impl Into<String> for Class {
-    fn into(self) -> String {
-        match self {
+impl From<Class> for String {
+    fn from(class: Class) -> String {
+        match class {
             Class::Barbarian => String::from("barbarian"),
             Class::Bard => String::from("bard"),
             Class::Cleric => String::from("cleric"),
@@ -140,3 +175,137 @@ impl Into<String> for Class {
         }
     }
 }
+
+#[derive(Clone, Copy)]
+pub enum ItemCategory {
+    Weapon = 0,
+    Armor = 1,
+    AdventuringGear = 2,
+    Tools = 3,
+    MountsAndVehicles = 4,
+    Ammunition = 5,
+    Potion = 6,
+    Ring = 7,
+    Rod = 8,
+    Scroll = 9,
+    Staff = 10,
+    Wand = 11,
+    WondrousItems = 12,
+}
+
+impl ItemCategory {
+    /// Every category, in list order.
+    pub const ALL: [ItemCategory; 13] = [
+        ItemCategory::Weapon,
+        ItemCategory::Armor,
+        ItemCategory::AdventuringGear,
+        ItemCategory::Tools,
+        ItemCategory::MountsAndVehicles,
+        ItemCategory::Ammunition,
+        ItemCategory::Potion,
+        ItemCategory::Ring,
+        ItemCategory::Rod,
+        ItemCategory::Scroll,
+        ItemCategory::Staff,
+        ItemCategory::Wand,
+        ItemCategory::WondrousItems,
+    ];
+}
+
+impl TryFrom<String> for ItemCategory {
+    type Error = String;
+
+    fn try_from(category: String) -> Result<Self, Self::Error> {
+        match category.as_str() {
+            "weapon" => Ok(ItemCategory::Weapon),
+            "armor" => Ok(ItemCategory::Armor),
+            "adventuring gear" => Ok(ItemCategory::AdventuringGear),
+            "tools" => Ok(ItemCategory::Tools),
+            "mounts and vehicles" => Ok(ItemCategory::MountsAndVehicles),
+            "ammunition" => Ok(ItemCategory::Ammunition),
+            "potion" => Ok(ItemCategory::Potion),
+            "ring" => Ok(ItemCategory::Ring),
+            "rod" => Ok(ItemCategory::Rod),
+            "scroll" => Ok(ItemCategory::Scroll),
+            "staff" => Ok(ItemCategory::Staff),
+            "wand" => Ok(ItemCategory::Wand),
+            "wondrous items" => Ok(ItemCategory::WondrousItems),
+            _ => Err(format!("Invalid item category {}", category)),
+        }
+    }
+}
+
+impl From<ItemCategory> for String {
+    fn from(category: ItemCategory) -> String {
+        match category {
+            ItemCategory::Weapon => String::from("weapon"),
+            ItemCategory::Armor => String::from("armor"),
+            ItemCategory::AdventuringGear => String::from("adventuring gear"),
+            ItemCategory::Tools => String::from("tools"),
+            ItemCategory::MountsAndVehicles => String::from("mounts and vehicles"),
+            ItemCategory::Ammunition => String::from("ammunition"),
+            ItemCategory::Potion => String::from("potion"),
+            ItemCategory::Ring => String::from("ring"),
+            ItemCategory::Rod => String::from("rod"),
+            ItemCategory::Scroll => String::from("scroll"),
+            ItemCategory::Staff => String::from("staff"),
+            ItemCategory::Wand => String::from("wand"),
+            ItemCategory::WondrousItems => String::from("wondrous items"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Rarity {
+    Common = 0,
+    Uncommon = 1,
+    Rare = 2,
+    VeryRare = 3,
+    Legendary = 4,
+    Artifact = 5,
+    Varies = 6,
+}
+
+impl Rarity {
+    /// Every rarity, in list order.
+    pub const ALL: [Rarity; 7] = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::VeryRare,
+        Rarity::Legendary,
+        Rarity::Artifact,
+        Rarity::Varies,
+    ];
+}
+
+impl TryFrom<String> for Rarity {
+    type Error = String;
+
+    fn try_from(rarity: String) -> Result<Self, Self::Error> {
+        match rarity.as_str() {
+            "common" => Ok(Rarity::Common),
+            "uncommon" => Ok(Rarity::Uncommon),
+            "rare" => Ok(Rarity::Rare),
+            "very rare" => Ok(Rarity::VeryRare),
+            "legendary" => Ok(Rarity::Legendary),
+            "artifact" => Ok(Rarity::Artifact),
+            "varies" => Ok(Rarity::Varies),
+            _ => Err(format!("Invalid rarity {}", rarity)),
+        }
+    }
+}
+
+impl From<Rarity> for String {
+    fn from(rarity: Rarity) -> String {
+        match rarity {
+            Rarity::Common => String::from("common"),
+            Rarity::Uncommon => String::from("uncommon"),
+            Rarity::Rare => String::from("rare"),
+            Rarity::VeryRare => String::from("very rare"),
+            Rarity::Legendary => String::from("legendary"),
+            Rarity::Artifact => String::from("artifact"),
+            Rarity::Varies => String::from("varies"),
+        }
+    }
+}
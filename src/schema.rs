@@ -17,5 +17,121 @@ table! {
         school -> Text,
         classes -> Text,
         subclasses -> Text,
+        source_id -> Integer,
+    }
+}
+
+table! {
+    equipment (id) {
+        id -> Integer,
+        name -> Text,
+        category -> Text,
+        subcategory -> Nullable<Text>,
+        cost -> Nullable<Text>,
+        weight -> Nullable<Float>,
+        damage -> Nullable<Text>,
+        armor_class -> Nullable<Text>,
+        properties -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    magic_items (id) {
+        id -> Integer,
+        name -> Text,
+        category -> Text,
+        rarity -> Text,
+        attunement -> Bool,
+        description -> Text,
+    }
+}
+
+table! {
+    glossary (id) {
+        id -> Integer,
+        term -> Text,
+        kind -> Text,
+        definition -> Text,
+    }
+}
+
+table! {
+    class_levels (id) {
+        id -> Integer,
+        class -> Text,
+        level -> Integer,
+        cantrips_known -> Nullable<Integer>,
+        spells_known -> Nullable<Integer>,
+        slots_1 -> Integer,
+        slots_2 -> Integer,
+        slots_3 -> Integer,
+        slots_4 -> Integer,
+        slots_5 -> Integer,
+        slots_6 -> Integer,
+        slots_7 -> Integer,
+        slots_8 -> Integer,
+        slots_9 -> Integer,
+    }
+}
+
+table! {
+    sources (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+table! {
+    campaigns (id) {
+        id -> Integer,
+        name -> Text,
+        current -> Bool,
+    }
+}
+
+table! {
+    campaign_sources (campaign_id, source_id) {
+        campaign_id -> Integer,
+        source_id -> Integer,
+        active -> Bool,
+    }
+}
+
+joinable!(spells -> sources (source_id));
+joinable!(campaign_sources -> campaigns (campaign_id));
+
+allow_tables_to_appear_in_same_query!(
+    spells,
+    sources,
+);
+
+allow_tables_to_appear_in_same_query!(
+    campaign_sources,
+    campaigns,
+);
+
+table! {
+    imports (id) {
+        id -> Integer,
+        imported_at -> Timestamp,
+        importer_version -> Text,
+        kind -> Text,
+        source_path -> Text,
+        content_hash -> Text,
+        records -> Integer,
+    }
+}
+
+table! {
+    favorites (name) {
+        name -> Text,
+    }
+}
+
+table! {
+    pane_sizes (pane) {
+        pane -> Text,
+        percent -> Integer,
     }
 }
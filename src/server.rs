@@ -0,0 +1,266 @@
+//! A small JSON API over the spell database, for other tools on the same
+//! machine. It only listens on localhost.
+
+use std::convert::TryFrom;
+use std::error::Error;
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use dnd_tui::db_utils::{count_spells, find_spell, load_sources, load_spell, query_spell,
+                      set_source_active, Query};
+use dnd_tui::models::{Class, School};
+
+type Reply = (u16, Value);
+
+fn error(status: u16, message: &str) -> Reply {
+    (status, json!({ "error": message }))
+}
+
+fn flag(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("expected true or false, not `{}`", value)),
+    }
+}
+
+fn number(value: &str) -> Result<i32, String> {
+    value.parse().map_err(|_| format!("expected a number, not `{}`", value))
+}
+
+/// Builds a `Query` from parameters like `?class=wizard&level=3`.
+fn parse_query(parameters: &str) -> Result<Query, String> {
+    let mut query = Query::default();
+    for (key, value) in form_urlencoded::parse(parameters.as_bytes()) {
+        let value = value.to_lowercase();
+        match key.as_ref() {
+            "class" => query.class = Some(Class::try_from(value)?),
+            "school" => query.school = Some(School::try_from(value)?),
+            "level" => query.level = Some(number(&value)?),
+            "character_level" => query.character_level = Some(number(&value)?),
+            "name" => query.name = Some(value),
+            "ritual" => query.ritual = Some(flag(&value)?),
+            "concentration" => query.concentration = Some(flag(&value)?),
+            key => return Err(format!("unknown parameter `{}`", key)),
+        }
+    }
+    Ok(query)
+}
+
+fn sources(conn: &SqliteConnection) -> Value {
+    load_sources(conn)
+        .into_iter()
+        .map(|source| json!({
+            "id": source.id,
+            "name": source.name,
+            "active": source.active,
+            "spells": count_spells(&source, conn),
+        }))
+        .collect()
+}
+
+fn route(request: &mut Request, writable: bool, conn: &SqliteConnection) -> Reply {
+    let url = request.url().to_owned();
+    let (path, parameters) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<String> = path.trim_matches('/')
+        .split('/')
+        .map(|segment| form_urlencoded::parse(format!("s={}", segment).as_bytes())
+            .next()
+            .map(|(_, segment)| segment.into_owned())
+            .unwrap_or_default())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["spells"]) => match parse_query(parameters) {
+            Ok(query) => {
+                let spells = query_spell(&query, conn);
+                (200, json!({ "query": query, "count": spells.len(), "spells": spells }))
+            },
+            Err(message) => error(400, &message),
+        },
+        // Spells are looked up by id, or by name like `show`
+        (Method::Get, ["spells", spell]) => {
+            let found = match spell.parse::<i32>() {
+                Ok(id) => load_spell(id, conn).ok_or_else(|| format!("No spell with id {}", id)),
+                Err(_) => find_spell(spell, conn),
+            };
+            match found {
+                Ok(spell) => (200, json!(spell)),
+                Err(message) => error(404, &message),
+            }
+        },
+        (Method::Get, ["sources"]) => (200, sources(conn)),
+        (Method::Put, ["sources", id]) if writable => {
+            let mut body = String::new();
+            let active = request.as_reader()
+                .read_to_string(&mut body)
+                .ok()
+                .and_then(|_| serde_json::from_str::<Value>(&body).ok())
+                .and_then(|body| body["active"].as_bool());
+            match (id.parse::<i32>(), active) {
+                (Ok(id), Some(active)) => {
+                    set_source_active(id, active, conn);
+                    (200, sources(conn))
+                },
+                _ => error(400, "expected a source id and a body like {\"active\": true}"),
+            }
+        },
+        (Method::Put, ["sources", _]) => error(403, "the server is read-only, start it with --writable"),
+        (Method::Get, _) => error(404, "not found"),
+        _ => error(405, "method not allowed"),
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
+
+/// Serves the API on `127.0.0.1:port` until the process is stopped. The
+/// connection is read-only unless `writable` is set, and browsers may only
+/// call the API from `allow_origin`, which may be `*`.
+pub fn serve(
+    port: u16,
+    writable: bool,
+    allow_origin: &str,
+    conn: SqliteConnection
+) -> Result<(), Box<dyn Error>> {
+    if !writable {
+        conn.execute("PRAGMA query_only = ON")?;
+    }
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|error| format!("Couldn't listen on port {}: {}", port, error))?;
+    println!(
+        "Serving {} spell API on http://{}",
+        if writable { "the" } else { "a read-only" },
+        server.server_addr()
+    );
+    answer(&server, writable, allow_origin, &conn);
+    Ok(())
+}
+
+fn answer(server: &Server, writable: bool, allow_origin: &str, conn: &SqliteConnection) {
+    for mut request in server.incoming_requests() {
+        let cors = [
+            header("Access-Control-Allow-Origin", allow_origin),
+            header("Access-Control-Allow-Methods", "GET, PUT, OPTIONS"),
+            header("Access-Control-Allow-Headers", "Content-Type"),
+        ];
+
+        // Browsers check with a preflight request before cross-origin PUTs
+        if *request.method() == Method::Options {
+            let mut response = Response::empty(204);
+            for header in cors.iter() {
+                response.add_header(header.clone());
+            }
+            request.respond(response).ok();
+            continue;
+        }
+
+        let (status, body) = route(&mut request, writable, conn);
+        let mut response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"));
+        for header in cors.iter() {
+            response.add_header(header.clone());
+        }
+        request.respond(response).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    use diesel::sqlite::SqliteConnection;
+    use serde_json::Value;
+    use tiny_http::{Method, Request, Server, TestRequest};
+
+    use dnd_tui::db_utils::{build_db, connect, ImportPaths};
+    use super::{answer, parse_query, route, Reply};
+
+    fn bundled_db() -> SqliteConnection {
+        let conn = connect(":memory:").unwrap();
+        build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+        conn
+    }
+
+    fn request(method: Method, path: &str, body: &'static str, writable: bool) -> Reply {
+        let mut request: Request = TestRequest::new()
+            .with_method(method)
+            .with_path(path)
+            .with_body(body)
+            .into();
+        route(&mut request, writable, &bundled_db())
+    }
+
+    #[test]
+    fn parses_queries() {
+        let query = parse_query("class=Wizard&school=evocation&level=3&ritual=no&name=fire%20ball")
+            .unwrap();
+        assert_eq!(query.class.map(String::from).as_deref(), Some("wizard"));
+        assert_eq!(query.school.map(String::from).as_deref(), Some("evocation"));
+        assert_eq!(query.level, Some(3));
+        assert_eq!(query.ritual, Some(false));
+        assert_eq!(query.name.as_deref(), Some("fire ball"));
+        assert!(parse_query("").unwrap().class.is_none());
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert!(parse_query("class=artificer").is_err());
+        assert!(parse_query("level=third").is_err());
+        assert!(parse_query("concentration=maybe").is_err());
+        assert_eq!(parse_query("color=red").err().unwrap(), "unknown parameter `color`");
+    }
+
+    #[test]
+    fn routes_lookups() {
+        let (status, body) = request(Method::Get, "/spells?name=fireball&level=3", "", false);
+        assert_eq!(status, 200);
+        assert_eq!(body["spells"][0]["name"], "Fireball");
+
+        let (status, body) = request(Method::Get, "/spells/Fireball", "", false);
+        assert_eq!((status, &body["name"]), (200, &Value::from("Fireball")));
+        assert_eq!(request(Method::Get, "/spells/no%20such%20spell", "", false).0, 404);
+    }
+
+    #[test]
+    fn routes_errors() {
+        assert_eq!(request(Method::Get, "/spells?level=third", "", false).0, 400);
+        assert_eq!(request(Method::Put, "/sources/0", r#"{"active": false}"#, false).0, 403);
+        assert_eq!(request(Method::Put, "/sources/0", "{}", true).0, 400);
+        assert_eq!(request(Method::Get, "/monsters", "", false).0, 404);
+        assert_eq!(request(Method::Delete, "/spells", "", false).0, 405);
+    }
+
+    #[test]
+    fn toggles_sources_when_writable() {
+        let (status, body) = request(Method::Put, "/sources/0", r#"{"active": false}"#, true);
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["active"], false);
+    }
+
+    #[test]
+    fn serves_spells_on_localhost() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || answer(&server, false, "*", &bundled_db()));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /spells?name=fireball HTTP/1.0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.0 200"));
+        assert!(head.contains("Access-Control-Allow-Origin: *"));
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert!(body["spells"].as_array().unwrap().iter().any(|spell| spell["name"] == "Fireball"));
+    }
+}
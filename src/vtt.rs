@@ -0,0 +1,336 @@
+//! Converts spells into the json virtual tabletops import.
+
+use log::warn;
+use serde_json::{json, Value};
+
+use dnd_tui::db_utils::Spell;
+use crate::export;
+
+/// Splits amounts like "10 minutes" or "150 feet" into the number and its
+/// lowercase unit.
+fn amount(text: &str) -> Option<(i64, String)> {
+    let mut words = text.split_whitespace();
+    let value = words.next()?.parse::<i64>().ok()?;
+    let unit = words.next()?.to_lowercase();
+    Some((value, unit))
+}
+
+/// Finds the first of `units` the amount's unit starts with, so plurals
+/// match too.
+fn unit<'a>(unit: &str, units: &[(&str, &'a str)], otherwise: &'a str) -> &'a str {
+    units.iter()
+        .find(|(prefix, _)| unit.starts_with(prefix))
+        .map_or(otherwise, |(_, units)| units)
+}
+
+/// The dnd5e system's code for the spell's school, left empty for schools it
+/// doesn't know.
+fn school(spell: &Spell) -> &'static str {
+    match spell.school.as_str() {
+        "abjuration" => "abj",
+        "conjuration" => "con",
+        "divination" => "div",
+        "enchantment" => "enc",
+        "evocation" => "evo",
+        "illusion" => "ill",
+        "necromancy" => "nec",
+        "transmutation" => "trs",
+        school => {
+            warn!("{} has the unknown school `{}`, leaving it out", spell.name, school);
+            ""
+        }
+    }
+}
+
+/// "1 reaction, which you take when..." becomes a reaction with the rest of
+/// the text as its condition.
+fn activation(spell: &Spell) -> Value {
+    let (time, condition) = match spell.casting_time.split_once(',') {
+        Some((time, condition)) => (time, condition.trim()),
+        None => (spell.casting_time.as_str(), ""),
+    };
+    let (cost, kind) = match amount(time) {
+        Some((cost, kind)) => (cost, unit(&kind, &[
+            ("action", "action"),
+            ("bonus", "bonus"),
+            ("reaction", "reaction"),
+            ("minute", "minute"),
+            ("hour", "hour"),
+            ("day", "day"),
+        ], "special")),
+        None => (0, "special"),
+    };
+    json!({ "type": kind, "cost": cost, "condition": condition })
+}
+
+fn duration(spell: &Spell) -> Value {
+    let text = spell.duration.to_lowercase();
+    let text = text.trim_start_matches("up to ");
+    let (value, units) = match amount(text) {
+        Some((value, units)) => (Some(value), unit(&units, &[
+            ("round", "round"),
+            ("minute", "minute"),
+            ("hour", "hour"),
+            ("day", "day"),
+            ("year", "year"),
+        ], "spec")),
+        None if text.starts_with("instantaneous") => (None, "inst"),
+        None if text.starts_with("until dispelled") => (None, "perm"),
+        None => (None, "spec"),
+    };
+    json!({ "value": value, "units": units })
+}
+
+fn range(spell: &Spell) -> Value {
+    let text = spell.range.as_deref().unwrap_or("").to_lowercase();
+    let (value, units) = match amount(&text) {
+        Some((value, units)) => (Some(value), unit(&units, &[
+            ("f", "ft"),
+            ("mile", "mi"),
+        ], "spec")),
+        None if text.starts_with("self") => (None, "self"),
+        None if text.starts_with("touch") => (None, "touch"),
+        None if text.starts_with("unlimited") => (None, "any"),
+        None => (None, "spec"),
+    };
+    json!({ "value": value, "long": null, "units": units })
+}
+
+/// A dnd5e system spell item, as taken by "Import Data" on an item in
+/// Foundry VTT.
+pub fn foundry_item(spell: &Spell, description: &str, higher_level: &str) -> Value {
+    let description = if higher_level.is_empty() {
+        description.to_owned()
+    } else {
+        format!("{}\n<p><strong>At Higher Levels.</strong></p>\n{}", description, higher_level)
+    };
+
+    json!({
+        "name": spell.name,
+        "type": "spell",
+        "system": {
+            "description": { "value": description, "chat": "", "unidentified": "" },
+            "source": spell.source,
+            "activation": activation(spell),
+            "duration": duration(spell),
+            "range": range(spell),
+            "level": spell.level,
+            "school": school(spell),
+            "components": {
+                "vocal": spell.verbal,
+                "somatic": spell.somatic,
+                "material": spell.material,
+                "ritual": spell.ritual,
+                "concentration": spell.concentration,
+            },
+            "materials": {
+                "value": spell.material_text.as_deref().unwrap_or(""),
+                "consumed": false,
+                "cost": 0,
+                "supply": 0,
+            },
+            "preparation": { "mode": "prepared", "prepared": false },
+        },
+    })
+}
+
+/// A spell in the layout of Roll20's compendium, where every attribute is a
+/// string.
+pub fn roll20_spell(spell: &Spell, description: &str, higher_level: &str) -> Value {
+    let flag = |set: bool| if set { "Yes" } else { "" };
+    let components: Vec<&str> = [(spell.verbal, "V"), (spell.somatic, "S"), (spell.material, "M")]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, component)| *component)
+        .collect();
+    let school = format!("{}{}", spell.school[..1].to_uppercase(), &spell.school[1..]);
+
+    json!({
+        "name": spell.name,
+        "content": description,
+        "data": {
+            "Category": "Spells",
+            "Level": spell.level.to_string(),
+            "School": school,
+            "Casting Time": spell.casting_time,
+            "Range": spell.range.as_deref().unwrap_or(""),
+            "Components": components.join(" "),
+            "Material": spell.material_text.as_deref().unwrap_or(""),
+            "Duration": spell.duration,
+            "Concentration": flag(spell.concentration),
+            "Ritual": flag(spell.ritual),
+            "Classes": export::classes(spell),
+            "Higher Spell Slot Desc": higher_level,
+            "Source": spell.source,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use dnd_tui::db_utils::Spell;
+    use super::foundry_item;
+
+    fn fireball() -> Spell {
+        Spell {
+            name: String::from("Fireball"),
+            range: Some(String::from("150 feet")),
+            verbal: true,
+            somatic: true,
+            material: true,
+            material_text: Some(String::from("A tiny ball of bat guano and sulfur.")),
+            duration: String::from("Instantaneous"),
+            casting_time: String::from("1 action"),
+            level: 3,
+            school: String::from("evocation"),
+            ..Spell::default()
+        }
+    }
+
+    fn shield() -> Spell {
+        Spell {
+            name: String::from("Shield"),
+            range: Some(String::from("Self")),
+            verbal: true,
+            somatic: true,
+            duration: String::from("1 round"),
+            casting_time: String::from("1 reaction, which you take when you are hit by an attack"),
+            level: 1,
+            school: String::from("abjuration"),
+            ..Spell::default()
+        }
+    }
+
+    fn bless() -> Spell {
+        Spell {
+            name: String::from("Bless"),
+            range: Some(String::from("30 feet")),
+            verbal: true,
+            somatic: true,
+            material: true,
+            material_text: Some(String::from("A sprinkling of holy water.")),
+            duration: String::from("Up to 1 minute"),
+            concentration: true,
+            casting_time: String::from("1 action"),
+            level: 1,
+            school: String::from("enchantment"),
+            ..Spell::default()
+        }
+    }
+
+    fn alarm() -> Spell {
+        Spell {
+            name: String::from("Alarm"),
+            range: Some(String::from("30 feet")),
+            verbal: true,
+            somatic: true,
+            material: true,
+            duration: String::from("8 hours"),
+            ritual: true,
+            casting_time: String::from("1 minute"),
+            level: 1,
+            school: String::from("abjuration"),
+            ..Spell::default()
+        }
+    }
+
+    #[test]
+    fn converts_fireball() {
+        let item = foundry_item(&fireball(), "<p>A bright streak.</p>", "<p>1d6 more.</p>");
+        let system = &item["system"];
+        assert_eq!(item["name"], "Fireball");
+        assert_eq!(
+            system["description"]["value"],
+            "<p>A bright streak.</p>\n<p><strong>At Higher Levels.</strong></p>\n<p>1d6 more.</p>",
+        );
+        assert_eq!(system["activation"], json!({ "type": "action", "cost": 1, "condition": "" }));
+        assert_eq!(system["duration"], json!({ "value": null, "units": "inst" }));
+        assert_eq!(system["range"], json!({ "value": 150, "long": null, "units": "ft" }));
+        assert_eq!(system["level"], 3);
+        assert_eq!(system["school"], "evo");
+        assert_eq!(system["components"], json!({
+            "vocal": true,
+            "somatic": true,
+            "material": true,
+            "ritual": false,
+            "concentration": false,
+        }));
+        assert_eq!(system["materials"]["value"], "A tiny ball of bat guano and sulfur.");
+    }
+
+    #[test]
+    fn splits_the_reaction_trigger_off() {
+        let system = &foundry_item(&shield(), "", "")["system"];
+        assert_eq!(system["activation"], json!({
+            "type": "reaction",
+            "cost": 1,
+            "condition": "which you take when you are hit by an attack",
+        }));
+        assert_eq!(system["duration"], json!({ "value": 1, "units": "round" }));
+        assert_eq!(system["range"], json!({ "value": null, "long": null, "units": "self" }));
+        assert_eq!(system["school"], "abj");
+        assert_eq!(system["components"]["material"], false);
+        assert_eq!(system["materials"]["value"], "");
+    }
+
+    #[test]
+    fn reads_concentration_durations() {
+        let system = &foundry_item(&bless(), "", "")["system"];
+        assert_eq!(system["duration"], json!({ "value": 1, "units": "minute" }));
+        assert_eq!(system["components"]["concentration"], true);
+        assert_eq!(system["school"], "enc");
+    }
+
+    #[test]
+    fn marks_rituals() {
+        let system = &foundry_item(&alarm(), "", "")["system"];
+        assert_eq!(system["activation"], json!({ "type": "minute", "cost": 1, "condition": "" }));
+        assert_eq!(system["duration"], json!({ "value": 8, "units": "hour" }));
+        assert_eq!(system["components"]["ritual"], true);
+        assert_eq!(system["components"]["concentration"], false);
+    }
+
+    #[test]
+    fn reads_other_units() {
+        let spell = |casting_time: &str, duration: &str, range: &str| Spell {
+            casting_time: String::from(casting_time),
+            duration: String::from(duration),
+            range: Some(String::from(range)),
+            school: String::from("divination"),
+            ..Spell::default()
+        };
+        let item = foundry_item(&spell("1 bonus action", "Until dispelled", "Touch"), "", "");
+        let system = &item["system"];
+        assert_eq!(system["activation"]["type"], "bonus");
+        assert_eq!(system["duration"]["units"], "perm");
+        assert_eq!(system["range"]["units"], "touch");
+
+        let item = foundry_item(&spell("12 hours", "Up to 24 hours", "1 mile"), "", "");
+        let system = &item["system"];
+        assert_eq!(system["activation"], json!({ "type": "hour", "cost": 12, "condition": "" }));
+        assert_eq!(system["duration"], json!({ "value": 24, "units": "hour" }));
+        assert_eq!(system["range"], json!({ "value": 1, "long": null, "units": "mi" }));
+
+        let item = foundry_item(&spell("Special", "Special", "Sight"), "", "");
+        let system = &item["system"];
+        assert_eq!(system["activation"], json!({ "type": "special", "cost": 0, "condition": "" }));
+        assert_eq!(system["duration"], json!({ "value": null, "units": "spec" }));
+        assert_eq!(system["range"], json!({ "value": null, "long": null, "units": "spec" }));
+    }
+
+    #[test]
+    fn maps_school_codes() {
+        let code = |school: &str| {
+            let spell = Spell { school: String::from(school), ..Spell::default() };
+            foundry_item(&spell, "", "")["system"]["school"].clone()
+        };
+        assert_eq!(code("conjuration"), "con");
+        assert_eq!(code("divination"), "div");
+        assert_eq!(code("illusion"), "ill");
+        assert_eq!(code("necromancy"), "nec");
+        assert_eq!(code("transmutation"), "trs");
+        assert_eq!(code("chronurgy"), "");
+    }
+}
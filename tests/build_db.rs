@@ -0,0 +1,233 @@
+//! Runs imports into in-memory databases and queries what they wrote.
+
+use dnd_tui::db_utils::{
+    add_campaign, build_db, connect, current_campaign, find_campaign, is_empty, load_campaigns,
+    load_glossary, load_sources, query_class_levels, query_items, query_spell, remove_campaign,
+    set_current_campaign, set_source_active, ImportError, ImportPaths, ItemQuery, Query,
+};
+use dnd_tui::models::{Class, School};
+use diesel::connection::SimpleConnection;
+use diesel::sqlite::SqliteConnection;
+use std::fs;
+use std::path::PathBuf;
+
+fn bundled_db() -> SqliteConnection {
+    let conn = connect(":memory:").unwrap();
+    build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+    conn
+}
+
+#[test]
+fn imports_the_bundled_srd() {
+    let conn = bundled_db();
+    assert!(!is_empty(&conn));
+
+    let spells = query_spell(&Query::default(), &conn);
+    assert_eq!(spells.len(), 319);
+}
+
+#[test]
+fn queries_the_bundled_srd() {
+    let conn = bundled_db();
+
+    let fireball = query_spell(&Query {
+        name: Some(String::from("Fireball")),
+        ..Query::default()
+    }, &conn);
+    assert!(fireball.iter().any(|spell| spell.name == "Fireball"));
+
+    let spells = query_spell(&Query {
+        class: Some(Class::Wizard),
+        school: Some(School::Evocation),
+        level: Some(3),
+        ..Query::default()
+    }, &conn);
+    assert!(spells.iter().any(|spell| spell.name == "Fireball"));
+    assert!(spells.iter().all(|spell| spell.level == 3 && spell.school == "evocation"));
+}
+
+#[test]
+fn reimporting_replaces_the_spells() {
+    let conn = bundled_db();
+    build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+
+    assert_eq!(query_spell(&Query::default(), &conn).len(), 319);
+}
+
+#[test]
+fn campaigns_keep_their_own_sources() {
+    let conn = bundled_db();
+    let default = current_campaign(&conn);
+    let srd = load_sources(&conn)[0].id;
+
+    let strahd = add_campaign("Curse of Strahd", &conn).unwrap();
+    assert!(add_campaign("curse of strahd", &conn).is_err());
+    set_current_campaign(strahd.id, &conn);
+    set_source_active(srd, false, &conn);
+    assert!(query_spell(&Query::default(), &conn).is_empty());
+    assert!(remove_campaign(&current_campaign(&conn), &conn).is_err());
+
+    set_current_campaign(default.id, &conn);
+    assert!(load_sources(&conn)[0].active);
+    assert_eq!(query_spell(&Query::default(), &conn).len(), 319);
+
+    remove_campaign(&find_campaign("Curse of Strahd", &conn).unwrap(), &conn).unwrap();
+    assert_eq!(load_campaigns(&conn).len(), 1);
+}
+
+/// A fresh directory for files to import, removed by the caller.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dnd_tui-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn imports_sources_dir_files_in_their_own_format() {
+    let dir = temp_dir("sources");
+    fs::write(dir.join("my-table.homebrew.json"), r#"[{
+        "name": "Zap Bolt", "description": "Zaps.", "level": 1, "school": "evocation",
+        "classes": ["wizard"], "casting_time": "1 action", "duration": "Instantaneous",
+        "components": "V, S"
+    }]"#).unwrap();
+
+    let conn = connect(":memory:").unwrap();
+    let paths = ImportPaths { sources_dir: Some(dir.clone()), ..ImportPaths::default() };
+    let imported = build_db(&conn, &paths, &mut |_| true);
+    fs::remove_dir_all(&dir).unwrap();
+    imported.unwrap();
+
+    let sources = load_sources(&conn);
+    assert!(sources.iter().any(|source| source.name == "my-table"));
+    let spells = query_spell(&Query {
+        name: Some(String::from("Zap Bolt")),
+        ..Query::default()
+    }, &conn);
+    assert_eq!(spells.len(), 1);
+}
+
+#[test]
+fn name_searches_match_wildcards_literally() {
+    let dir = temp_dir("wildcards");
+    fs::write(dir.join("odd-names.homebrew.json"), r#"[{
+        "name": "100% Zap_Bolt", "description": "Zaps.", "level": 1, "school": "evocation",
+        "classes": ["wizard"], "casting_time": "1 action", "duration": "Instantaneous"
+    }]"#).unwrap();
+
+    let conn = connect(":memory:").unwrap();
+    let paths = ImportPaths { sources_dir: Some(dir.clone()), ..ImportPaths::default() };
+    let imported = build_db(&conn, &paths, &mut |_| true);
+    fs::remove_dir_all(&dir).unwrap();
+    imported.unwrap();
+
+    let named = |name: &str| -> Vec<String> {
+        query_spell(&Query { name: Some(String::from(name)), ..Query::default() }, &conn)
+            .into_iter()
+            .map(|spell| spell.name)
+            .collect()
+    };
+    assert_eq!(named("%"), ["100% Zap_Bolt"]);
+    assert_eq!(named("_"), ["100% Zap_Bolt"]);
+    assert_eq!(named("0% Zap_B"), ["100% Zap_Bolt"]);
+    assert!(named("Fire_all").is_empty());
+    assert!(named("Fire%ll").is_empty());
+    assert!(named("\\").is_empty());
+}
+
+#[test]
+fn imports_items_rules_and_levels() {
+    let dir = temp_dir("items");
+    fs::write(dir.join("equipment.json"), r#"[{
+        "name": "Club", "equipment_category": {"name": "Weapon"}, "category_range": "Simple Melee",
+        "cost": {"quantity": 1, "unit": "sp"}, "weight": 2,
+        "damage": {"damage_dice": "1d4", "damage_type": {"name": "Bludgeoning"}},
+        "properties": [{"name": "Light"}, {"name": "Monk"}]
+    }]"#).unwrap();
+    fs::write(dir.join("magic-items.json"), r#"[{
+        "name": "Cloak of Protection", "equipment_category": {"name": "Wondrous Items"},
+        "rarity": {"name": "Uncommon"},
+        "desc": ["Wondrous item, uncommon (requires attunement)", "+1 to AC."]
+    }]"#).unwrap();
+    fs::write(dir.join("conditions.json"), r#"[
+        {"name": "Blinded", "desc": ["- A blinded creature can't see."]}
+    ]"#).unwrap();
+    fs::write(dir.join("rules.json"), r###"[
+        {"name": "Cover", "desc": "## Cover\nWalls and trees can provide cover."}
+    ]"###).unwrap();
+    fs::write(dir.join("levels.json"), r#"[
+        {"index": "wizard-1", "level": 1, "class": {"name": "Wizard"},
+         "spellcasting": {"cantrips_known": 3, "spell_slots_level_1": 2}},
+        {"index": "wizard-evocation-2", "level": 2, "class": {"name": "Wizard"},
+         "subclass": {"name": "Evocation"}}
+    ]"#).unwrap();
+
+    let conn = connect(":memory:").unwrap();
+    let paths = ImportPaths {
+        equipment: Some(dir.join("equipment.json")),
+        magic_items: Some(dir.join("magic-items.json")),
+        conditions: Some(dir.join("conditions.json")),
+        rules: Some(dir.join("rules.json")),
+        levels: Some(dir.join("levels.json")),
+        ..ImportPaths::default()
+    };
+    let imported = build_db(&conn, &paths, &mut |_| true);
+    fs::remove_dir_all(&dir).unwrap();
+    imported.unwrap();
+
+    let items = query_items(&ItemQuery::default(), &conn);
+    let names: Vec<&str> = items.iter().map(|item| item.name()).collect();
+    assert_eq!(names, ["Club", "Cloak of Protection"]);
+    let glossary = load_glossary(&conn);
+    assert_eq!(glossary.len(), 2);
+    assert!(glossary.iter().any(|entry| entry.term == "Cover"
+        && entry.definition == "Walls and trees can provide cover."));
+    let levels = query_class_levels(Class::Wizard, &conn);
+    assert_eq!(levels.len(), 1);
+    assert_eq!((levels[0].cantrips_known, levels[0].slots_1), (Some(3), 2));
+}
+
+#[test]
+fn reports_every_invalid_item() {
+    let dir = temp_dir("invalid-items");
+    fs::write(dir.join("magic-items.json"), r#"[
+        {"name": "Bag of Holding", "equipment_category": {"name": "Wondrous Items"}},
+        {"name": "Cloak of Protection", "equipment_category": {"name": "Wondrous Items"},
+         "rarity": {"name": "Uncommon"}},
+        {"equipment_category": {"name": "Wondrous Items"}, "rarity": {"name": "Rare"}}
+    ]"#).unwrap();
+
+    let conn = connect(":memory:").unwrap();
+    let paths = ImportPaths {
+        magic_items: Some(dir.join("magic-items.json")),
+        ..ImportPaths::default()
+    };
+    let imported = build_db(&conn, &paths, &mut |_| true);
+    fs::remove_dir_all(&dir).unwrap();
+
+    match imported {
+        Err(ImportError::Invalid { errors, .. }) => assert_eq!(errors, [
+            "record 1 (Bag of Holding): missing `rarity`",
+            "record 3 (unnamed): missing `name`",
+        ]),
+        other => panic!("expected invalid records, got {:?}", other.err()),
+    }
+    assert!(is_empty(&conn));
+}
+
+#[test]
+fn reports_database_errors() {
+    let conn = bundled_db();
+    conn.batch_execute("
+        create trigger no_spells before insert on spells
+        begin select raise(abort, 'spells are read-only'); end;
+    ").unwrap();
+
+    match build_db(&conn, &ImportPaths::default(), &mut |_| true) {
+        Err(error @ ImportError::Database(_)) => {
+            assert_eq!(error.to_string(), "Database error: spells are read-only");
+        },
+        other => panic!("expected a database error, got {:?}", other.err()),
+    }
+    // Nothing was written
+    assert_eq!(query_spell(&Query::default(), &conn).len(), 319);
+}
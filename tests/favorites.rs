@@ -0,0 +1,28 @@
+//! Favorites are kept by spell name, apart from the imported spells.
+
+use dnd_tui::db_utils::{build_db, connect, load_favorites, set_favorite, ImportPaths};
+
+#[test]
+fn toggles_favorites() {
+    let conn = connect(":memory:").unwrap();
+    assert!(load_favorites(&conn).is_empty());
+
+    set_favorite("Fireball", true, &conn);
+    set_favorite("Counterspell", true, &conn);
+    set_favorite("Fireball", true, &conn);
+    assert_eq!(load_favorites(&conn), ["Counterspell", "Fireball"]);
+
+    set_favorite("Counterspell", false, &conn);
+    set_favorite("Wish", false, &conn);
+    assert_eq!(load_favorites(&conn), ["Fireball"]);
+}
+
+#[test]
+fn favorites_outlast_a_reimport() {
+    let conn = connect(":memory:").unwrap();
+    build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+    set_favorite("Fireball", true, &conn);
+
+    build_db(&conn, &ImportPaths::default(), &mut |_| true).unwrap();
+    assert_eq!(load_favorites(&conn), ["Fireball"]);
+}
@@ -0,0 +1,66 @@
+//! Runs `dnd_tui import` against a database in a temporary directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh directory holding an empty config, removed by the caller.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dnd_tui-cli-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "").unwrap();
+    dir
+}
+
+fn import(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dnd_tui"))
+        .arg("--config").arg(dir.join("config.toml"))
+        .arg("--database").arg(dir.join("5e.db"))
+        .arg("import")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn fails_on_invalid_records() {
+    let dir = temp_dir("invalid");
+    let spells = dir.join("homebrew.json");
+    fs::write(&spells, r#"[
+        {"name": "Zap", "description": "Zaps.", "duration": "Instantaneous",
+         "casting_time": "1 action", "level": 1, "school": "evocation"},
+        {"name": "Unzap", "description": "Unzaps.", "duration": "Instantaneous",
+         "casting_time": "1 action", "level": 1, "school": "chronurgy"}
+    ]"#).unwrap();
+
+    let output = import(&dir, &["--source", spells.to_str().unwrap(), "--format", "homebrew"]);
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let dry_run = import(&dir, &["--source", spells.to_str().unwrap(), "--format", "homebrew", "--dry-run"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("has 1 invalid record(s)"), "{}", stderr);
+    assert!(stderr.contains("record 2 (Unzap): unknown school `chronurgy`"), "{}", stderr);
+    assert_eq!(dry_run.status.code(), Some(1));
+}
+
+#[test]
+fn rejects_file_options_without_a_source() {
+    let dir = temp_dir("no-source");
+    let outputs: Vec<Output> = [
+        &["--format", "open5e"][..],
+        &["--name", "Deep Magic"],
+        &["--replace"],
+        &["--merge"],
+        &["--dry-run"],
+    ].iter().map(|args| import(&dir, args)).collect();
+    let database = dir.join("5e.db").exists();
+    fs::remove_dir_all(&dir).unwrap();
+
+    for output in outputs {
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--source"));
+    }
+    // Nothing was imported
+    assert!(!database);
+}